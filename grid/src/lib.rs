@@ -0,0 +1,205 @@
+use std::ops::{Index, IndexMut};
+
+const NEIGHBORS_4: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const NEIGHBORS_8: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+// the width shared by every row; a shorter or longer row would shift every
+// cell after it into the wrong place
+fn row_width(mut lengths: impl Iterator<Item = usize>) -> usize {
+    let width = lengths.next().unwrap_or(0);
+
+    if let Some((row, len)) = lengths.enumerate().find(|&(_, len)| len != width) {
+        panic!("row {} has {} cells, but row 0 has {}", row + 1, len, width);
+    }
+
+    width
+}
+
+/// A 2-D grid stored row by row in a single `Vec`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Grid {
+            cells: vec![value; width * height],
+            width,
+            height,
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid from a newline-delimited map, one cell per character.
+    ///
+    /// Panics if the lines aren't all the same length.
+    pub fn from_char_map(input: &str, f: impl Fn(char) -> T) -> Self {
+        let lines = input.trim().lines().map(str::trim).collect::<Vec<_>>();
+        let width = row_width(lines.iter().map(|line| line.chars().count()));
+
+        Grid {
+            cells: lines.iter().flat_map(|line| line.chars()).map(f).collect(),
+            width,
+            height: lines.len(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn offset(&self, row: usize, col: usize) -> Option<usize> {
+        if row < self.height && col < self.width {
+            Some(row * self.width + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        self.offset(row, col).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        self.offset(row, col).map(move |i| &mut self.cells[i])
+    }
+
+    /// Returns the old value, or `None` if the cell is outside the grid.
+    pub fn set(&mut self, row: usize, col: usize, value: T) -> Option<T> {
+        self.get_mut(row, col)
+            .map(|cell| std::mem::replace(cell, value))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    fn neighbors<'a>(
+        &'a self,
+        row: usize,
+        col: usize,
+        offsets: &'a [(isize, isize)],
+    ) -> impl Iterator<Item = (usize, usize, &'a T)> {
+        offsets.iter().filter_map(move |&(d_row, d_col)| {
+            let row = (row as isize + d_row) as usize;
+            let col = (col as isize + d_col) as usize;
+            self.get(row, col).map(|cell| (row, col, cell))
+        })
+    }
+
+    /// Up, left, right and down, skipping anything outside the grid.
+    pub fn neighbors_4(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize, &T)> {
+        self.neighbors(row, col, &NEIGHBORS_4)
+    }
+
+    /// Every surrounding cell, diagonals included.
+    pub fn neighbors_8(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize, &T)> {
+        self.neighbors(row, col, &NEIGHBORS_8)
+    }
+}
+
+/// Panics if the rows aren't all the same length.
+impl<T> From<Vec<Vec<T>>> for Grid<T> {
+    fn from(rows: Vec<Vec<T>>) -> Self {
+        let height = rows.len();
+        let width = row_width(rows.iter().map(Vec::len));
+
+        Grid {
+            cells: rows.into_iter().flatten().collect(),
+            width,
+            height,
+        }
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        self.get(row, col)
+            .unwrap_or_else(|| panic!("({}, {}) is outside the grid", row, col))
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        self.get_mut(row, col)
+            .unwrap_or_else(|| panic!("({}, {}) is outside the grid", row, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_char_map() {
+        let grid = Grid::from_char_map("#..\n.#.\n", |c| c == '#');
+
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.get(1, 1), Some(&true));
+        assert_eq!(grid.get(1, 2), Some(&false));
+        assert_eq!(grid.get(2, 0), None);
+        assert_eq!(
+            grid.rows().collect::<Vec<_>>(),
+            vec![&[true, false, false][..], &[false, true, false][..]]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "row 1 has 2 cells, but row 0 has 3")]
+    fn parse_ragged_char_map() {
+        Grid::from_char_map("#..\n.#\n", |c| c == '#');
+    }
+
+    #[test]
+    #[should_panic(expected = "row 2 has 4 cells, but row 0 has 2")]
+    fn from_ragged_rows() {
+        let _ = Grid::from(vec![vec![1, 2], vec![3, 4], vec![5, 6, 7, 8]]);
+    }
+
+    #[test]
+    fn set_and_index() {
+        let mut grid = Grid::new(2, 2, 0);
+
+        assert_eq!(grid.set(0, 1, 5), Some(0));
+        assert_eq!(grid.set(2, 0, 5), None);
+        grid[(1, 0)] += 3;
+
+        assert_eq!(grid, Grid::from(vec![vec![0, 5], vec![3, 0]]));
+    }
+
+    #[test]
+    fn neighbors_in_corner() {
+        let grid = Grid::from(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]);
+
+        let values = |neighbors: Vec<(usize, usize, &i32)>| {
+            neighbors
+                .into_iter()
+                .map(|(_, _, &value)| value)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(values(grid.neighbors_4(0, 0).collect()), vec![2, 4]);
+        assert_eq!(values(grid.neighbors_8(0, 0).collect()), vec![2, 4, 5]);
+        assert_eq!(values(grid.neighbors_4(1, 1).collect()), vec![2, 4, 6, 8]);
+        assert_eq!(values(grid.neighbors_8(1, 1).collect()).len(), 8);
+    }
+}
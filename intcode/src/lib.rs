@@ -0,0 +1,1725 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    io::{self, Write},
+    ops::{Add, Mul},
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "channels")]
+use std::sync::mpsc::{Receiver, Sender};
+
+/// The integer type a machine's memory is made of.
+pub trait Word:
+    Copy + PartialOrd + Add<Output = Self> + Mul<Output = Self> + From<i8> + fmt::Debug + fmt::Display
+{
+    /// `None` if the value is negative, or too big for a `usize`.
+    fn to_usize(self) -> Option<usize>;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                fn to_usize(self) -> Option<usize> {
+                    usize::try_from(self).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(i16, i32, i64, i128, isize);
+
+/// Where the `store` opcode pulls its values from.
+pub trait InputSource<N = isize> {
+    fn next_input(&mut self) -> Option<N>;
+}
+
+/// Where the `push_output` opcode sends its values to.
+pub trait OutputSink<N = isize> {
+    fn emit(&mut self, value: N) -> Result<(), IntcodeError>;
+}
+
+// A bare value is read over and over again
+impl<N: Word> InputSource<N> for N {
+    fn next_input(&mut self) -> Option<N> {
+        Some(*self)
+    }
+}
+
+impl<N: Word> InputSource<N> for VecDeque<N> {
+    fn next_input(&mut self) -> Option<N> {
+        self.pop_front()
+    }
+}
+
+// lets a boxed iterator be cloned so the machine can be forked
+trait CloneIterator<N>: Iterator<Item = N> {
+    fn clone_box(&self) -> Box<dyn CloneIterator<N>>;
+}
+
+impl<N, T: Iterator<Item = N> + Clone + 'static> CloneIterator<N> for T {
+    fn clone_box(&self) -> Box<dyn CloneIterator<N>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Any iterator boxed up so it can be fed to `IntcodeMachine::with_input`.
+pub struct IterInput<N = isize>(Box<dyn CloneIterator<N>>);
+
+impl<N: Word> InputSource<N> for IterInput<N> {
+    fn next_input(&mut self) -> Option<N> {
+        self.0.next()
+    }
+}
+
+impl<N> Clone for IterInput<N> {
+    fn clone(&self) -> Self {
+        IterInput(self.0.clone_box())
+    }
+}
+
+impl<N> fmt::Debug for IterInput<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IterInput")
+    }
+}
+
+#[cfg(feature = "channels")]
+impl<N: Word> InputSource<N> for Receiver<N> {
+    fn next_input(&mut self) -> Option<N> {
+        self.recv().ok()
+    }
+}
+
+impl<N: Word> OutputSink<N> for Vec<N> {
+    fn emit(&mut self, value: N) -> Result<(), IntcodeError> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+impl<N: Word> OutputSink<N> for VecDeque<N> {
+    fn emit(&mut self, value: N) -> Result<(), IntcodeError> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+/// Hands every output to a closure as soon as it's produced.
+pub struct FnOutput<F>(F);
+
+impl<N: Word, F: FnMut(N)> OutputSink<N> for FnOutput<F> {
+    fn emit(&mut self, value: N) -> Result<(), IntcodeError> {
+        (self.0)(value);
+        Ok(())
+    }
+}
+
+impl<F> fmt::Debug for FnOutput<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FnOutput")
+    }
+}
+
+#[cfg(feature = "channels")]
+impl<N: Word> OutputSink<N> for Sender<N> {
+    fn emit(&mut self, value: N) -> Result<(), IntcodeError> {
+        self.send(value)
+            .map_err(|err| IntcodeError::IoError(err.to_string()))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IntcodeError {
+    UnknownMode(usize),
+    UnknownOpcode { opcode: usize, address: usize },
+    UnexpectedArgs,
+    IoError(String),
+    MissingHalt { address: usize },
+    OutOfBounds { addr: usize, len: usize },
+    InvalidAddress(String),
+    InvalidInstruction { value: String, address: usize },
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownMode(code) => write!(f, "unknown code: {}", code),
+            Self::UnknownOpcode { opcode, address } => {
+                write!(f, "unknown instruction: {} at address {}", opcode, address)
+            }
+            Self::UnexpectedArgs => write!(f, "wrong number of arguments for instruction"),
+            Self::IoError(message) => write!(f, "io error: {}", message),
+            Self::MissingHalt { address } => {
+                write!(f, "ran off the end of the program at address {}", address)
+            }
+            Self::OutOfBounds { addr, len } => {
+                write!(f, "address {} is outside memory of length {}", addr, len)
+            }
+            Self::InvalidAddress(value) => write!(f, "{} can't be used as an address", value),
+            Self::InvalidInstruction { value, address } => {
+                write!(f, "{} at address {} isn't an instruction", value, address)
+            }
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+#[derive(Debug)]
+pub enum Mode {
+    Position,
+    Value,
+    Relative,
+}
+
+impl Mode {
+    pub fn from_code(code: usize) -> Result<Self, IntcodeError> {
+        match code {
+            0 => Ok(Self::Position),
+            1 => Ok(Self::Value),
+            2 => Ok(Self::Relative),
+            x => Err(IntcodeError::UnknownMode(x)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Position => "pos",
+            Self::Value => "imm",
+            Self::Relative => "rel",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Args<T> {
+    Zero,
+    One(T),
+    Two(T, T),
+    Three(T, T, T),
+}
+
+// padded out to the longest instruction, so iterating doesn't allocate
+type ArgsIter<T> = std::iter::Flatten<std::array::IntoIter<Option<T>, 3>>;
+
+impl<T> IntoIterator for Args<T> {
+    type Item = T;
+    type IntoIter = ArgsIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let args = match self {
+            Args::Zero => [None, None, None],
+            Args::One(a) => [Some(a), None, None],
+            Args::Two(a, b) => [Some(a), Some(b), None],
+            Args::Three(a, b, c) => [Some(a), Some(b), Some(c)],
+        };
+
+        IntoIterator::into_iter(args).flatten()
+    }
+}
+
+impl<T> Args<T> {
+    fn len(&self) -> usize {
+        match self {
+            Args::Zero => 0,
+            Args::One(_) => 1,
+            Args::Two(..) => 2,
+            Args::Three(..) => 3,
+        }
+    }
+
+    // the arguments an instruction takes, or `UnexpectedArgs` if there's a
+    // different number of them
+    fn exactly<const K: usize>(self) -> Result<[T; K], IntcodeError> {
+        if self.len() != K {
+            return Err(IntcodeError::UnexpectedArgs);
+        }
+
+        let mut args = self.into_iter();
+        Ok(std::array::from_fn(|_| {
+            args.next().expect("there are exactly K arguments")
+        }))
+    }
+
+    // `f` is also given each argument's position
+    fn try_map<U, E>(self, mut f: impl FnMut(usize, T) -> Result<U, E>) -> Result<Args<U>, E> {
+        Ok(match self {
+            Args::Zero => Args::Zero,
+            Args::One(a) => Args::One(f(0, a)?),
+            Args::Two(a, b) => Args::Two(f(0, a)?, f(1, b)?),
+            Args::Three(a, b, c) => Args::Three(f(0, a)?, f(1, b)?, f(2, c)?),
+        })
+    }
+}
+
+impl<T: Copy> IntoIterator for &Args<T> {
+    type Item = T;
+    type IntoIter = ArgsIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (*self).into_iter()
+    }
+}
+
+#[derive(Debug)]
+struct DecodedInstruction {
+    opcode: usize,
+    param_modes: [Mode; 3],
+}
+
+impl DecodedInstruction {
+    fn decode(instruction: usize) -> Result<Self, IntcodeError> {
+        let mode = |i| Mode::from_code(instruction / 10_usize.pow(i) % 10);
+
+        Ok(DecodedInstruction {
+            opcode: instruction % 100,
+            param_modes: [mode(2)?, mode(3)?, mode(4)?],
+        })
+    }
+}
+
+// "dst" marks the argument an instruction writes to
+fn describe_opcode(opcode: usize) -> (&'static str, &'static [&'static str]) {
+    match opcode {
+        1 => ("ADD", &["a", "b", "dst"]),
+        2 => ("MUL", &["a", "b", "dst"]),
+        3 => ("STORE", &["dst"]),
+        4 => ("PUSH_OUTPUT", &["a"]),
+        5 => ("JUMP_IF_TRUE", &["cond", "target"]),
+        6 => ("JUMP_IF_FALSE", &["cond", "target"]),
+        7 => ("LESS_THAN", &["a", "b", "dst"]),
+        8 => ("EQUALS", &["a", "b", "dst"]),
+        9 => ("MUTATE_REL_BASE", &["delta"]),
+        99 => ("HALT", &[]),
+        _ => ("UNKNOWN", &[]),
+    }
+}
+
+/// One line of a listing produced by `disassemble`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisassembledInstruction {
+    pub address: usize,
+    pub raw: isize,
+    pub name: String,
+    /// `(mode_name, raw_arg)` for each argument
+    pub args: Vec<(String, isize)>,
+    // what a position-mode argument points at in the original program
+    values: Vec<Option<isize>>,
+}
+
+impl DisassembledInstruction {
+    fn data(address: usize, raw: isize) -> Self {
+        DisassembledInstruction {
+            address,
+            raw,
+            name: "DATA".to_string(),
+            args: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.name == "DATA" {
+            return write!(f, "{:>3}: DATA {}", self.address, self.raw);
+        }
+
+        write!(f, "{:>3}: {:<4}", self.address, self.name)?;
+
+        let (_, labels) = describe_opcode(self.raw as usize % 100);
+        for ((label, (mode, arg)), value) in labels.iter().zip(&self.args).zip(&self.values) {
+            match (*label, value) {
+                ("dst", _) => write!(f, " →{}[{}]", mode, arg)?,
+                (_, Some(value)) => write!(f, " {}[{}]={}", mode, arg, value)?,
+                (_, None) => write!(f, " {}[{}]", mode, arg)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes a program into a listing without running it. Words that can't be
+/// decoded as an instruction are labeled as data.
+pub fn disassemble(program: &[isize]) -> Vec<DisassembledInstruction> {
+    let mut listing = Vec::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        let raw = program[address];
+        let decoded = DecodedInstruction::decode(raw as usize)
+            .ok()
+            .filter(|_| raw >= 0)
+            .map(|decoded| (describe_opcode(decoded.opcode), decoded.param_modes));
+
+        match decoded {
+            Some(((name, labels), modes))
+                if name != "UNKNOWN" && address + labels.len() < program.len() =>
+            {
+                let end = address + 1 + labels.len();
+                let raw_args = &program[address + 1..end];
+                let values = raw_args
+                    .iter()
+                    .zip(&modes)
+                    .map(|(&arg, mode)| match mode {
+                        Mode::Position if arg >= 0 => {
+                            Some(program.get(arg as usize).copied().unwrap_or(0))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+
+                listing.push(DisassembledInstruction {
+                    address,
+                    raw,
+                    name: name.to_string(),
+                    args: modes
+                        .iter()
+                        .zip(raw_args)
+                        .map(|(mode, &arg)| (mode.name().to_string(), arg))
+                        .collect(),
+                    values,
+                });
+                address = end;
+            }
+            _ => {
+                listing.push(DisassembledInstruction::data(address, raw));
+                address += 1;
+            }
+        }
+    }
+
+    listing
+}
+
+const MAX_MEMORY_GROWTH: usize = 1024;
+
+// called with the address and value of every memory access
+#[derive(Clone)]
+struct Hook<N>(Rc<dyn Fn(usize, N)>);
+
+impl<N> fmt::Debug for Hook<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hook")
+    }
+}
+
+// where debug traces are written
+#[derive(Clone)]
+struct DebugSink(Rc<RefCell<dyn Write>>);
+
+impl fmt::Debug for DebugSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DebugSink")
+    }
+}
+
+/// What a single executed instruction did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepInfo {
+    pub opcode: usize,
+    pub cmd_ptr_before: usize,
+    pub cmd_ptr_after: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RunResult {
+    Halted,
+    BudgetExceeded,
+}
+
+/// The serializable part of a machine, IO not included.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MachineState<N = isize> {
+    // the original program, so a restored machine can still be reset
+    program: Vec<N>,
+    memory: Vec<N>,
+    sparse_memory: HashMap<usize, N>,
+    cmd_ptr: usize,
+    rel_base: N,
+    halted: bool,
+}
+
+/// Everything needed to put a machine back to an earlier point of execution.
+/// The output sink is not part of a snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MachineSnapshot<I, N = isize> {
+    state: MachineState<N>,
+    input: I,
+}
+
+#[derive(Debug)]
+pub struct IntcodeMachine<I: InputSource<N>, O: OutputSink<N>, N: Word = isize> {
+    // kept around so the machine can be reset
+    program: Vec<N>,
+    memory: Vec<N>,
+    // anything written too far past the end of memory lives here
+    sparse_memory: HashMap<usize, N>,
+    cmd_ptr: usize,
+    rel_base: N,
+    halted: bool,
+    input: I,
+    output: O,
+    // only set by the instruction just executed
+    last_output: Option<N>,
+    instruction_count: u64,
+    counts: HashMap<usize, u64>,
+    debug: Option<DebugSink>,
+    memory_limit: Option<usize>,
+    read_hook: Option<Hook<N>>,
+    write_hook: Option<Hook<N>>,
+}
+
+/// A machine working with plain `isize` memory.
+pub type DefaultMachine<I, O> = IntcodeMachine<I, O, isize>;
+
+impl<I: InputSource<N>, O: OutputSink<N>, N: Word> IntcodeMachine<I, O, N> {
+    pub fn new(program: &[N], input: I, output: O) -> Self {
+        IntcodeMachine {
+            program: program.to_vec(),
+            memory: program.to_vec(),
+            sparse_memory: HashMap::new(),
+            cmd_ptr: 0,
+            rel_base: N::from(0),
+            halted: false,
+            input,
+            output,
+            last_output: None,
+            instruction_count: 0,
+            counts: HashMap::new(),
+            debug: None,
+            memory_limit: None,
+            read_hook: None,
+            write_hook: None,
+        }
+    }
+
+    /// Picks a machine back up from a saved state with fresh IO attached.
+    pub fn from_state(state: MachineState<N>, input: I, output: O) -> Self {
+        let mut machine = Self::new(&[], input, output);
+        machine.load_state(state);
+        machine
+    }
+
+    fn load_state(&mut self, state: MachineState<N>) {
+        self.program = state.program;
+        self.memory = state.memory;
+        self.sparse_memory = state.sparse_memory;
+        self.cmd_ptr = state.cmd_ptr;
+        self.rel_base = state.rel_base;
+        self.halted = state.halted;
+        self.last_output = None;
+    }
+
+    pub fn save_state(&self) -> MachineState<N> {
+        MachineState {
+            program: self.program.clone(),
+            memory: self.memory.clone(),
+            sparse_memory: self.sparse_memory.clone(),
+            cmd_ptr: self.cmd_ptr,
+            rel_base: self.rel_base,
+            halted: self.halted,
+        }
+    }
+
+    /// Prints every instruction before it's executed.
+    pub fn with_debug(self) -> Self {
+        self.with_debug_output(io::stdout())
+    }
+
+    /// Like `with_debug`, but writes each instruction to `sink`.
+    pub fn with_debug_output(mut self, sink: impl Write + 'static) -> Self {
+        self.debug = Some(DebugSink(Rc::new(RefCell::new(sink))));
+        self
+    }
+
+    /// Puts memory back to the original program and rewinds to the start.
+    /// Input and output are left alone.
+    pub fn reset(&mut self) {
+        self.memory.clear();
+        self.memory.extend_from_slice(&self.program);
+        self.sparse_memory.clear();
+        self.cmd_ptr = 0;
+        self.rel_base = N::from(0);
+        self.halted = false;
+        self.last_output = None;
+        self.instruction_count = 0;
+        self.counts.clear();
+    }
+
+    /// Makes the running program fail with `OutOfBounds` instead of growing
+    /// memory when it touches an address at or past `len`.
+    pub fn with_memory_limit(mut self, len: usize) -> Self {
+        self.memory_limit = Some(len);
+        self
+    }
+
+    /// Calls `hook` with the address and value of every read.
+    pub fn on_read(mut self, hook: impl Fn(usize, N) + 'static) -> Self {
+        self.read_hook = Some(Hook(Rc::new(hook)));
+        self
+    }
+
+    /// Calls `hook` with the address and value of every write.
+    pub fn on_write(mut self, hook: impl Fn(usize, N) + 'static) -> Self {
+        self.write_hook = Some(Hook(Rc::new(hook)));
+        self
+    }
+
+    /// Uninitialized addresses read as `0`.
+    pub fn read(&self, addr: usize) -> N {
+        let value = self.peek(addr);
+
+        if let Some(Hook(hook)) = &self.read_hook {
+            hook(addr, value);
+        }
+
+        value
+    }
+
+    // reads without calling the read hook
+    fn peek(&self, addr: usize) -> N {
+        match self.memory.get(addr) {
+            Some(&value) => value,
+            None => self
+                .sparse_memory
+                .get(&addr)
+                .copied()
+                .unwrap_or_else(|| N::from(0)),
+        }
+    }
+
+    /// Writes just past the end of memory grow it, anything further out is
+    /// stored sparsely.
+    pub fn write(&mut self, addr: usize, val: N) {
+        if let Some(Hook(hook)) = &self.write_hook {
+            hook(addr, val);
+        }
+
+        let len = self.memory.len();
+
+        if addr < len {
+            self.memory[addr] = val;
+        } else if addr - len < MAX_MEMORY_GROWTH {
+            let sparse_memory = &mut self.sparse_memory;
+            let grown =
+                (len..addr).map(|addr| sparse_memory.remove(&addr).unwrap_or_else(|| N::from(0)));
+            self.memory.extend(grown);
+            self.memory.push(val);
+            self.sparse_memory.remove(&addr);
+        } else {
+            self.sparse_memory.insert(addr, val);
+        }
+    }
+
+    // negative addresses are always an error, memory limit or not
+    fn to_address(value: N) -> Result<usize, IntcodeError> {
+        value
+            .to_usize()
+            .ok_or_else(|| IntcodeError::InvalidAddress(value.to_string()))
+    }
+
+    fn check_bounds(&self, addr: usize) -> Result<(), IntcodeError> {
+        match self.memory_limit {
+            Some(len) if addr >= len => Err(IntcodeError::OutOfBounds { addr, len }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like `read`, but respects the memory limit.
+    pub fn try_read(&self, addr: usize) -> Result<N, IntcodeError> {
+        self.check_bounds(addr)?;
+        Ok(self.read(addr))
+    }
+
+    /// Like `write`, but respects the memory limit.
+    pub fn try_write(&mut self, addr: usize, val: N) -> Result<(), IntcodeError> {
+        self.check_bounds(addr)?;
+        self.write(addr, val);
+        Ok(())
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    pub fn into_output(self) -> O {
+        self.output
+    }
+
+    fn decode_instruction(&mut self) -> Result<DecodedInstruction, IntcodeError> {
+        if self.cmd_ptr >= self.memory.len() && !self.sparse_memory.contains_key(&self.cmd_ptr) {
+            return Err(IntcodeError::MissingHalt {
+                address: self.cmd_ptr,
+            });
+        }
+
+        let value = self.try_read(self.cmd_ptr)?;
+        let instruction = value
+            .to_usize()
+            .ok_or_else(|| IntcodeError::InvalidInstruction {
+                value: value.to_string(),
+                address: self.cmd_ptr,
+            })?;
+        self.cmd_ptr += 1;
+
+        DecodedInstruction::decode(instruction)
+    }
+
+    // decode_instruction has already moved past the opcode
+    fn unknown_opcode(&self, opcode: usize) -> IntcodeError {
+        IntcodeError::UnknownOpcode {
+            opcode,
+            address: self.cmd_ptr - 1,
+        }
+    }
+
+    fn get_raw_args(&self, opcode: usize) -> Result<Args<N>, IntcodeError> {
+        let arg = |i| self.try_read(self.cmd_ptr + i);
+
+        Ok(match opcode {
+            1 | 2 | 7 | 8 => Args::Three(arg(0)?, arg(1)?, arg(2)?),
+            5 | 6 => Args::Two(arg(0)?, arg(1)?),
+            3 | 4 | 9 => Args::One(arg(0)?),
+            99 => Args::Zero,
+            x => return Err(self.unknown_opcode(x)),
+        })
+    }
+
+    fn build_args(
+        &self,
+        raw_args: Args<N>,
+        modes: &[Mode; 3],
+    ) -> Result<Args<usize>, IntcodeError> {
+        let cmd_ptr = self.cmd_ptr;
+
+        raw_args.try_map(|i, raw_arg| match modes[i] {
+            Mode::Position => Self::to_address(raw_arg),
+            Mode::Value => Ok(cmd_ptr + i),
+            Mode::Relative => Self::to_address(self.rel_base + raw_arg),
+        })
+    }
+
+    // returns whether the machine should keep running
+    fn apply(&mut self, opcode: usize, args: Args<usize>) -> Result<bool, IntcodeError> {
+        match opcode {
+            1 => self.add(args),
+            2 => self.mul(args),
+            3 => self.store(args),
+            4 => self.push_output(args),
+            5 => self.jump_if_true(args),
+            6 => self.jump_if_false(args),
+            7 => self.less_than(args),
+            8 => self.equals(args),
+            9 => self.mutate_rel_base(args),
+            99 => self.halt(args),
+            x => Err(self.unknown_opcode(x)),
+        }
+    }
+
+    fn trace(&self, opcode: usize, args: &Args<usize>) -> String {
+        let ptr = self.cmd_ptr - 1;
+        let (name, labels) = describe_opcode(opcode);
+
+        let args = labels
+            .iter()
+            .zip(args)
+            .map(|(&label, addr)| match label {
+                "dst" => format!(" → {}[{}]", label, addr),
+                _ => format!(" {}[{}]={}", label, addr, self.peek(addr)),
+            })
+            .collect::<String>();
+
+        format!("[ptr={}] {}{} ({})", ptr, name, args, self.peek(ptr))
+    }
+
+    fn decode_step(&mut self) -> Result<(usize, Args<usize>), IntcodeError> {
+        let DecodedInstruction {
+            opcode,
+            param_modes,
+        } = self.decode_instruction()?;
+        let raw_args = self.get_raw_args(opcode)?;
+
+        Ok((opcode, self.build_args(raw_args, &param_modes)?))
+    }
+
+    fn execute_step(&mut self) -> Result<Option<StepInfo>, IntcodeError> {
+        self.last_output = None;
+
+        if self.halted {
+            return Ok(None);
+        }
+
+        let cmd_ptr_before = self.cmd_ptr;
+        let (opcode, args) = self.decode_step()?;
+
+        if let Some(DebugSink(sink)) = &self.debug {
+            writeln!(sink.borrow_mut(), "{}", self.trace(opcode, &args))
+                .map_err(|err| IntcodeError::IoError(err.to_string()))?;
+        }
+
+        let should_continue = self.apply(opcode, args)?;
+        self.instruction_count += 1;
+        *self.counts.entry(opcode).or_insert(0) += 1;
+
+        Ok(if should_continue {
+            Some(StepInfo {
+                opcode,
+                cmd_ptr_before,
+                cmd_ptr_after: self.cmd_ptr,
+            })
+        } else {
+            None
+        })
+    }
+
+    /// Executes a single instruction, returning `None` once the machine halts.
+    pub fn step(&mut self) -> Result<Option<StepInfo>, IntcodeError> {
+        self.execute_step()
+    }
+
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        while self.execute_step()?.is_some() {}
+
+        Ok(())
+    }
+
+    /// Runs at most `max_instructions` more instructions.
+    pub fn run_with_budget(&mut self, max_instructions: u64) -> Result<RunResult, IntcodeError> {
+        for _ in 0..max_instructions {
+            if self.execute_step()?.is_none() {
+                return Ok(RunResult::Halted);
+            }
+        }
+
+        Ok(RunResult::BudgetExceeded)
+    }
+
+    /// Counts every executed instruction, including the final `HALT`.
+    pub fn instructions_executed(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// How many times each opcode has been executed so far.
+    pub fn instruction_counts(&self) -> &HashMap<usize, u64> {
+        &self.counts
+    }
+
+    /// Runs until the next value is output, returning `None` if the machine
+    /// halts first. The value is still sent to the output sink.
+    pub fn run_until_output(&mut self) -> Result<Option<N>, IntcodeError> {
+        while self.execute_step()?.is_some() {
+            if let Some(value) = self.last_output.take() {
+                return Ok(Some(value));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs to completion and returns every value output along the way.
+    pub fn collect_outputs(&mut self) -> Result<Vec<N>, IntcodeError> {
+        let mut outputs = Vec::new();
+
+        while let Some(value) = self.run_until_output()? {
+            outputs.push(value);
+        }
+
+        Ok(outputs)
+    }
+
+    /// Consumes the machine and yields each output as it is produced, only
+    /// running far enough to answer each call to `next`. Stops after the
+    /// first error.
+    pub fn outputs(mut self) -> impl Iterator<Item = Result<N, IntcodeError>> {
+        let mut failed = false;
+
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+
+            let next = self.run_until_output().transpose();
+            failed = matches!(next, Some(Err(_)));
+            next
+        })
+    }
+
+    fn add(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [a_pos, b_pos, output_pos] = args.exactly()?;
+        self.try_write(output_pos, self.try_read(a_pos)?.add(self.try_read(b_pos)?))?;
+        self.cmd_ptr += 3;
+        Ok(true)
+    }
+
+    fn mul(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [a_pos, b_pos, output_pos] = args.exactly()?;
+        self.try_write(output_pos, self.try_read(a_pos)?.mul(self.try_read(b_pos)?))?;
+        self.cmd_ptr += 3;
+        Ok(true)
+    }
+
+    fn store(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [storage_position] = args.exactly()?;
+        let value = self
+            .input
+            .next_input()
+            .ok_or_else(|| IntcodeError::IoError("ran out of input".to_string()))?;
+        self.try_write(storage_position, value)?;
+        self.cmd_ptr += 1;
+        Ok(true)
+    }
+
+    fn push_output(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [data_position] = args.exactly()?;
+        let value = self.try_read(data_position)?;
+        self.output.emit(value)?;
+        self.last_output = Some(value);
+        self.cmd_ptr += 1;
+        Ok(true)
+    }
+
+    fn jump_if_true(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [should_jump_pos, instruction_position] = args.exactly()?;
+        self.cmd_ptr = if self.try_read(should_jump_pos)? != N::from(0) {
+            Self::to_address(self.try_read(instruction_position)?)?
+        } else {
+            self.cmd_ptr + 2
+        };
+
+        Ok(true)
+    }
+
+    fn jump_if_false(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [jump_on_zero_pos, instruction_position] = args.exactly()?;
+        self.cmd_ptr = if self.try_read(jump_on_zero_pos)? == N::from(0) {
+            Self::to_address(self.try_read(instruction_position)?)?
+        } else {
+            self.cmd_ptr + 2
+        };
+
+        Ok(true)
+    }
+
+    fn less_than(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [a_pos, b_pos, output_pos] = args.exactly()?;
+        self.try_write(
+            output_pos,
+            N::from((self.try_read(a_pos)? < self.try_read(b_pos)?) as i8),
+        )?;
+        self.cmd_ptr += 3;
+        Ok(true)
+    }
+
+    fn equals(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [a_pos, b_pos, output_pos] = args.exactly()?;
+        self.try_write(
+            output_pos,
+            N::from((self.try_read(a_pos)? == self.try_read(b_pos)?) as i8),
+        )?;
+        self.cmd_ptr += 3;
+        Ok(true)
+    }
+
+    fn mutate_rel_base(&mut self, args: Args<usize>) -> Result<bool, IntcodeError> {
+        let [delta_pos] = args.exactly()?;
+        let delta = self.try_read(delta_pos)?;
+        self.rel_base = self.rel_base + delta;
+        self.cmd_ptr += 1;
+        Ok(true)
+    }
+
+    fn halt(&mut self, _args: Args<usize>) -> Result<bool, IntcodeError> {
+        self.halted = true;
+        Ok(false)
+    }
+}
+
+impl<N: Word + 'static> IntcodeMachine<IterInput<N>, Vec<N>, N> {
+    pub fn with_input<T>(program: &[N], input: T) -> Self
+    where
+        T: IntoIterator<Item = N>,
+        T::IntoIter: Clone + 'static,
+    {
+        Self::new(program, IterInput(Box::new(input.into_iter())), Vec::new())
+    }
+}
+
+impl<I: InputSource> IntcodeMachine<I, VecDeque<isize>> {
+    /// Drains the ASCII output so far, stopping at the first value that isn't
+    /// ASCII so the caller can deal with it.
+    pub fn read_ascii(&mut self) -> String {
+        let mut text = String::new();
+
+        while let Some(&value) = self.output.front() {
+            if !(0..=127).contains(&value) {
+                break;
+            }
+
+            text.push(value as u8 as char);
+            self.output.pop_front();
+        }
+
+        text
+    }
+}
+
+impl<N: Word, F: FnMut(N)> IntcodeMachine<VecDeque<N>, FnOutput<F>, N> {
+    /// Calls `sink` with each output in order. Input is queued with
+    /// `push_input`.
+    pub fn with_output_sink(program: &[N], sink: F) -> Self {
+        Self::new(program, VecDeque::new(), FnOutput(sink))
+    }
+}
+
+impl<O: OutputSink<N>, N: Word> IntcodeMachine<VecDeque<N>, O, N> {
+    pub fn push_input(&mut self, value: N) {
+        self.input.push_back(value);
+    }
+
+    pub fn push_inputs(&mut self, values: impl IntoIterator<Item = N>) {
+        self.input.extend(values);
+    }
+}
+
+impl<O: OutputSink> IntcodeMachine<VecDeque<isize>, O> {
+    /// Queues the line's characters followed by a newline.
+    pub fn feed_ascii_line(&mut self, line: &str) {
+        self.push_inputs(line.bytes().map(isize::from));
+        self.push_input(10);
+    }
+
+    /// Queues `input` as ASCII and runs to completion. Anything output outside
+    /// the ASCII range is written out as a number on its own line.
+    pub fn run_ascii(&mut self, input: &str) -> Result<String, IntcodeError> {
+        self.push_inputs(input.bytes().map(isize::from));
+
+        let mut text = String::new();
+        for value in self.collect_outputs()? {
+            if (0..=127).contains(&value) {
+                text.push(value as u8 as char);
+            } else {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&format!("{}\n", value));
+            }
+        }
+
+        Ok(text)
+    }
+}
+
+impl<I: InputSource<N> + Clone, O: OutputSink<N>, N: Word> IntcodeMachine<I, O, N> {
+    pub fn snapshot(&self) -> MachineSnapshot<I, N> {
+        MachineSnapshot {
+            state: self.save_state(),
+            input: self.input.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snap: MachineSnapshot<I, N>) {
+        self.load_state(snap.state);
+        self.input = snap.input;
+    }
+}
+
+impl<I: InputSource<N> + Clone, O: OutputSink<N> + Default, N: Word> IntcodeMachine<I, O, N> {
+    /// Clones the machine along with its pending input. The fork starts with
+    /// an empty output sink of its own.
+    pub fn fork(&self) -> Self {
+        IntcodeMachine {
+            program: self.program.clone(),
+            memory: self.memory.clone(),
+            sparse_memory: self.sparse_memory.clone(),
+            cmd_ptr: self.cmd_ptr,
+            rel_base: self.rel_base,
+            halted: self.halted,
+            input: self.input.clone(),
+            output: O::default(),
+            last_output: None,
+            instruction_count: self.instruction_count,
+            counts: self.counts.clone(),
+            debug: self.debug.clone(),
+            memory_limit: self.memory_limit,
+            read_hook: self.read_hook.clone(),
+            write_hook: self.write_hook.clone(),
+        }
+    }
+}
+
+impl<I: InputSource<N>, O: OutputSink<N>, N: Word> Iterator for IntcodeMachine<I, O, N> {
+    type Item = Result<StepInfo, IntcodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.execute_step().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a writer the test can still read from after handing it to a machine
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn echo_input() {
+        let mut computer = IntcodeMachine::with_input(&[3, 0, 4, 0, 99], [42]);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![42]);
+    }
+
+    #[test]
+    fn copy_itself() {
+        let program = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(computer.collect_outputs(), Ok(program.to_vec()));
+    }
+
+    #[cfg(feature = "channels")]
+    #[test]
+    fn copy_itself_over_channels() {
+        use std::sync::mpsc::channel;
+
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let (tx_computer, rx_outer) = channel();
+        let (tx_outer, rx_computer) = channel();
+        tx_outer.send(1).expect("failed to send input");
+
+        let mut computer = IntcodeMachine::new(&program, rx_computer, tx_computer);
+        computer.run().unwrap();
+        drop(computer);
+
+        let output: Vec<isize> = rx_outer.into_iter().collect();
+
+        assert_eq!(output, program);
+    }
+
+    #[test]
+    fn copy_itself_within_budget() {
+        let program = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(computer.run_with_budget(10_000), Ok(RunResult::Halted));
+        assert!(computer.instructions_executed() < 10_000);
+        assert_eq!(computer.output(), &program.to_vec());
+    }
+
+    #[test]
+    fn infinite_loop_exceeds_budget() {
+        let program = [1105, 1, 0, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(computer.run_with_budget(500), Ok(RunResult::BudgetExceeded));
+        assert_eq!(computer.instructions_executed(), 500);
+    }
+
+    #[test]
+    fn count_each_opcode() {
+        let program = [1, 0, 0, 0, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.run().unwrap();
+
+        let counts = computer.instruction_counts();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&1], 1);
+        assert_eq!(counts[&99], 1);
+    }
+
+    #[test]
+    fn output_16_digits() {
+        let program = [1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.run().unwrap();
+
+        let output = computer.output()[0];
+
+        assert_eq!(
+            (0_u32..)
+                .take_while(|exp| 10_isize.pow(*exp) <= output)
+                .count(),
+            16
+        );
+    }
+
+    #[test]
+    fn output_16_digits_with_i128() {
+        let program: [i128; 8] = [1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
+
+        let mut computer = IntcodeMachine::<_, _, i128>::with_input(&program, vec![]);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![34_915_192 * 34_915_192]);
+    }
+
+    #[test]
+    fn output_middle() {
+        let program: [isize; 3] = [104, 1_125_899_906_842_624, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![program[1]]);
+    }
+
+    #[test]
+    fn disassemble_output_middle() {
+        let program = [104, 1_125_899_906_842_624, 99];
+
+        let listing = disassemble(&program);
+
+        assert_eq!(listing.len(), 2);
+        assert_eq!(
+            listing
+                .iter()
+                .filter(|line| line.name == "PUSH_OUTPUT")
+                .map(|line| line.address)
+                .collect::<Vec<_>>(),
+            vec![0]
+        );
+        assert_eq!((listing[1].address, listing[1].name.as_str()), (2, "HALT"));
+        assert_eq!(
+            listing[0].to_string(),
+            "  0: PUSH_OUTPUT imm[1125899906842624]"
+        );
+    }
+
+    #[test]
+    fn disassemble_labels_data() {
+        let program = [1, 1, 1, 3, 99, 42];
+
+        let listing = disassemble(&program)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            listing,
+            vec![
+                "  0: ADD  pos[1]=1 pos[1]=1 →pos[3]",
+                "  4: HALT",
+                "  5: DATA 42"
+            ]
+        );
+    }
+
+    #[test]
+    fn debug_trace() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let output = SharedBuffer::default();
+        let reads = Rc::new(RefCell::new(0));
+        let read_count = Rc::clone(&reads);
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![])
+            .with_debug_output(output.clone())
+            .on_read(move |_, _| *read_count.borrow_mut() += 1);
+        computer.run().unwrap();
+
+        let text = String::from_utf8(output.0.borrow().clone()).unwrap();
+        let trace = text.lines().collect::<Vec<_>>();
+
+        assert_eq!(trace[0], "[ptr=0] MUTATE_REL_BASE delta[1]=1 (109)");
+        assert_eq!(trace[1], "[ptr=2] PUSH_OUTPUT a[0]=109 (204)");
+        assert_eq!(trace[2], "[ptr=4] ADD a[100]=0 b[6]=1 → dst[100] (1001)");
+        assert_eq!(trace.last().unwrap(), &"[ptr=15] HALT (99)");
+        assert_eq!(trace.len() as u64, computer.instructions_executed());
+
+        // tracing doesn't show up as extra reads
+        let quiet_reads = Rc::new(RefCell::new(0));
+        let read_count = Rc::clone(&quiet_reads);
+        IntcodeMachine::with_input(&program, vec![])
+            .on_read(move |_, _| *read_count.borrow_mut() += 1)
+            .run()
+            .unwrap();
+        assert_eq!(reads, quiet_reads);
+    }
+
+    #[test]
+    fn args_by_count() {
+        assert_eq!(Args::Three(1, 2, 3).exactly(), Ok([1, 2, 3]));
+        assert_eq!(Args::Zero::<usize>.exactly(), Ok([]));
+        assert_eq!(
+            Args::Two(1, 2).exactly::<3>(),
+            Err(IntcodeError::UnexpectedArgs)
+        );
+        assert_eq!(Args::Two(1, 2).into_iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            Args::Two(5, 7).try_map(|i, x| Ok::<_, ()>(i * x)),
+            Ok(Args::Two(0, 7))
+        );
+    }
+
+    #[test]
+    fn equal_to_8_in_position_mode() {
+        let program = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+
+        let mut computer = IntcodeMachine::with_input(&program, [8]);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![1]);
+    }
+
+    #[test]
+    fn write_far_past_the_program() {
+        let program = [1101, 5, 6, 1_000_000, 4, 1_000_000, 4, 999_999, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![11, 0]);
+        assert_eq!(computer.read(1_000_000), 11);
+    }
+
+    #[test]
+    fn grow_memory_past_the_program() {
+        let program = [1101, 5, 6, 20, 1101, 1, 2, 21, 4, 20, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![11]);
+        assert_eq!(computer.memory.len(), 22);
+        assert_eq!(computer.read(21), 3);
+    }
+
+    #[test]
+    fn step_reports_opcode() {
+        let program = [1, 0, 0, 0, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(
+            computer.step().unwrap(),
+            Some(StepInfo {
+                opcode: 1,
+                cmd_ptr_before: 0,
+                cmd_ptr_after: 4,
+            })
+        );
+        assert_eq!(computer.step().unwrap(), None);
+    }
+
+    #[test]
+    fn run_until_each_output() {
+        let program = [104, 4, 104, 1, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(computer.run_until_output().unwrap(), Some(4));
+        assert_eq!(computer.run_until_output().unwrap(), Some(1));
+        assert_eq!(computer.run_until_output().unwrap(), None);
+        assert_eq!(computer.output(), &vec![4, 1]);
+    }
+
+    #[test]
+    fn output_from_step_is_not_repeated() {
+        let program = [104, 7, 1101, 1, 1, 20, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.step().unwrap();
+
+        assert_eq!(computer.run_until_output(), Ok(None));
+        assert_eq!(computer.output(), &vec![7]);
+    }
+
+    #[test]
+    fn stays_halted() {
+        let program = [104, 5, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(computer.run_until_output(), Ok(Some(5)));
+        assert_eq!(computer.run_until_output(), Ok(None));
+        assert_eq!(computer.run_until_output(), Ok(None));
+        assert_eq!(computer.step(), Ok(None));
+        assert_eq!(computer.run(), Ok(()));
+        assert_eq!(computer.instructions_executed(), 2);
+
+        computer.reset();
+        assert_eq!(computer.run_until_output(), Ok(Some(5)));
+    }
+
+    #[test]
+    fn resume_with_pushed_input() {
+        let program = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+
+        let outputs = [7, 8, 9]
+            .iter()
+            .map(|&value| {
+                let mut computer = IntcodeMachine::new(&program, VecDeque::new(), Vec::new());
+                computer.push_input(value);
+                let output = computer.run_until_output().unwrap();
+                assert_eq!(computer.run_until_output().unwrap(), None);
+                output
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(outputs, vec![Some(0), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn unknown_mode() {
+        let program = [31102, 0, 0, 0, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(computer.run(), Err(IntcodeError::UnknownMode(3)));
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let program = [42, 0, 0, 0, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::UnknownOpcode {
+                opcode: 42,
+                address: 0
+            })
+        );
+    }
+
+    #[test]
+    fn missing_halt() {
+        let program = [1, 0, 0, 0];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::MissingHalt { address: 4 })
+        );
+    }
+
+    #[test]
+    fn write_out_of_bounds() {
+        let program = [1, 0, 0, 99999, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]).with_memory_limit(5);
+
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::OutOfBounds {
+                addr: 99999,
+                len: 5
+            })
+        );
+    }
+
+    #[test]
+    fn negative_addresses() {
+        let mut computer = IntcodeMachine::with_input(&[1, -1, 0, 0, 99], vec![]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::InvalidAddress("-1".to_string()))
+        );
+
+        // the relative base can be pushed below zero, but not used there
+        let mut computer = IntcodeMachine::with_input(&[109, -5, 2201, 0, 0, 0, 99], vec![]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::InvalidAddress("-5".to_string()))
+        );
+
+        let mut computer = IntcodeMachine::with_input(&[1105, 1, -3, 99], vec![]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::InvalidAddress("-3".to_string()))
+        );
+
+        let mut computer = IntcodeMachine::with_input(&[-1, 0, 0, 0, 99], vec![]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::InvalidInstruction {
+                value: "-1".to_string(),
+                address: 0
+            })
+        );
+    }
+
+    #[test]
+    fn read_instruction_out_of_bounds() {
+        let program = [1, 0, 0, 0, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]).with_memory_limit(3);
+
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::OutOfBounds { addr: 3, len: 3 })
+        );
+    }
+
+    #[test]
+    fn run_out_of_input() {
+        let program = [3, 0, 3, 1, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, [1]);
+
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::IoError("ran out of input".to_string()))
+        );
+    }
+
+    #[test]
+    fn echo_inputs_until_exhausted() {
+        let program = [3, 7, 4, 7, 1105, 1, 0, 0];
+
+        let mut computer = IntcodeMachine::with_input(&program, [1, 5, 8]);
+
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::IoError("ran out of input".to_string()))
+        );
+        assert_eq!(computer.output(), &vec![1, 5, 8]);
+    }
+
+    #[test]
+    fn restore_snapshot() {
+        let program = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let input: VecDeque<_> = vec![8].into();
+
+        let mut fresh = IntcodeMachine::new(&program, input.clone(), Vec::new());
+        fresh.run().unwrap();
+
+        let mut computer = IntcodeMachine::new(&program, input, Vec::new());
+        let start = computer.snapshot();
+        computer.by_ref().take(2).for_each(|step| {
+            step.unwrap();
+        });
+        assert_ne!(computer.snapshot(), start);
+
+        computer.restore(start.clone());
+        assert_eq!(computer.snapshot(), start);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), fresh.output());
+    }
+
+    #[test]
+    fn resume_from_saved_state() {
+        let program = [1, 1, 1, 4, 99, 5, 6, 0, 99];
+
+        let mut fresh = IntcodeMachine::with_input(&program, vec![]);
+        fresh.run().unwrap();
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.step().unwrap();
+        computer.step().unwrap();
+
+        let saved = serde_json::to_string(&computer.save_state()).unwrap();
+        let state: MachineState = serde_json::from_str(&saved).unwrap();
+        assert_eq!(state, computer.save_state());
+
+        let mut resumed = IntcodeMachine::from_state(state, 0, Vec::new());
+        resumed.run().unwrap();
+
+        assert_eq!(resumed.read(0), 30);
+        assert_eq!(resumed.save_state(), fresh.save_state());
+
+        // resetting goes back to the original program, not the saved memory
+        resumed.reset();
+        assert_eq!(resumed.read(0), 1);
+        resumed.run().unwrap();
+        assert_eq!(resumed.read(0), 30);
+    }
+
+    #[test]
+    fn fork_with_queued_input() {
+        let program = [3, 0, 4, 0, 3, 0, 4, 0, 99];
+        let input: VecDeque<_> = vec![1].into();
+
+        let mut computer = IntcodeMachine::new(&program, input, Vec::new());
+        computer.step().unwrap();
+        computer.step().unwrap();
+
+        let mut fork = computer.fork();
+        computer.push_input(5);
+        fork.push_input(7);
+        computer.run().unwrap();
+        fork.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![1, 5]);
+        assert_eq!(fork.output(), &vec![7]);
+    }
+
+    #[test]
+    fn fork_with_iterator_input() {
+        let program = [3, 0, 4, 0, 3, 0, 4, 0, 99];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![1, 2]);
+        computer.step().unwrap();
+        computer.step().unwrap();
+
+        let mut fork = computer.fork();
+        computer.run().unwrap();
+        fork.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![1, 2]);
+        assert_eq!(fork.output(), &vec![2]);
+    }
+
+    #[test]
+    fn queued_inputs() {
+        let program = [3, 0, 3, 1, 4, 0, 4, 1, 99];
+        let input: VecDeque<_> = vec![7, 42].into();
+
+        let mut computer = IntcodeMachine::new(&program, input, VecDeque::new());
+        computer.run().unwrap();
+
+        assert_eq!(computer.into_output(), vec![7, 42]);
+    }
+
+    #[test]
+    fn push_several_inputs() {
+        let program = [3, 0, 3, 1, 3, 2, 4, 0, 4, 1, 4, 2, 99];
+
+        let mut computer = IntcodeMachine::new(&program, VecDeque::new(), Vec::new());
+        computer.push_input(1);
+        computer.push_inputs(vec![2, 3]);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn output_to_closure() {
+        let program = [104, 1, 104, 2, 104, 3, 99];
+        let mut outputs = Vec::new();
+
+        IntcodeMachine::with_output_sink(&program, |value| outputs.push(value))
+            .run()
+            .unwrap();
+
+        assert_eq!(outputs, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn reset_reruns_program() {
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![]);
+        computer.run().unwrap();
+        assert_eq!(computer.read(0), 3500);
+
+        computer.reset();
+        assert_eq!(computer.read(0), 1);
+        computer.write(9, 10);
+        computer.run().unwrap();
+
+        assert_eq!(computer.read(0), 2500);
+        assert_eq!(computer.instructions_executed(), 3);
+    }
+
+    #[test]
+    fn hooks_see_memory_access() {
+        let program = [1, 5, 6, 7, 99, 3, 4, 0];
+        let reads = Rc::new(RefCell::new(Vec::new()));
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let (read_log, write_log) = (Rc::clone(&reads), Rc::clone(&writes));
+
+        let mut computer = IntcodeMachine::with_input(&program, vec![])
+            .on_read(move |addr, value| read_log.borrow_mut().push((addr, value)))
+            .on_write(move |addr, value| write_log.borrow_mut().push((addr, value)));
+        computer.run().unwrap();
+
+        assert!(reads.borrow().contains(&(5, 3)));
+        assert!(reads.borrow().contains(&(6, 4)));
+        assert_eq!(*writes.borrow(), vec![(7, 7)]);
+    }
+
+    // echoes its input until it reads a newline
+    const ECHO_LINE: [isize; 14] = [
+        3, 100, 1008, 100, 10, 101, 1005, 101, 14, 4, 100, 1105, 1, 0,
+    ];
+
+    #[test]
+    fn run_ascii_echo() {
+        let mut program = ECHO_LINE.to_vec();
+        program.push(99);
+
+        let mut computer = IntcodeMachine::new(&program, VecDeque::new(), Vec::new());
+
+        assert_eq!(computer.run_ascii("hello\n"), Ok("hello".to_string()));
+    }
+
+    #[test]
+    fn feed_ascii_line_then_run() {
+        let mut program = ECHO_LINE.to_vec();
+        program.extend(&[104, 1000, 99]);
+
+        let mut computer = IntcodeMachine::new(&program, VecDeque::new(), Vec::new());
+        computer.feed_ascii_line("hello");
+
+        assert_eq!(computer.run_ascii(""), Ok("hello\n1000\n".to_string()));
+    }
+
+    #[test]
+    fn read_ascii_round_trip() {
+        let program = [
+            3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 0, 104, 1000, 99,
+        ];
+
+        let mut computer = IntcodeMachine::new(&program, VecDeque::new(), VecDeque::new());
+        computer.feed_ascii_line("north");
+        computer.run().unwrap();
+
+        assert_eq!(computer.read_ascii(), "north\n");
+        assert_eq!(computer.read_ascii(), "");
+        assert_eq!(computer.output(), &VecDeque::from(vec![1000]));
+    }
+
+    #[test]
+    fn phase_setting_before_queued_inputs() {
+        let program = [3, 0, 3, 1, 4, 0, 4, 1, 99];
+
+        let mut computer = IntcodeMachine::new(&program, VecDeque::new(), Vec::new());
+        computer.push_input(7);
+        computer.push_input(42);
+        computer.run().unwrap();
+
+        assert_eq!(computer.output(), &vec![7, 42]);
+    }
+}
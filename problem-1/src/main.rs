@@ -1,20 +1,69 @@
-use std::{cmp::max, error::Error, fs};
+use std::{cmp::max, error::Error, fmt, fs};
+
+/// Fuel is `mass / divisor - offset`, but never less than nothing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FuelRule {
+    divisor: i64,
+    offset: i64,
+}
+
+#[derive(Debug, PartialEq)]
+struct FuelRuleError {
+    divisor: i64,
+    offset: i64,
+}
+
+impl fmt::Display for FuelRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mass / {} - {} needs a positive divisor and has to shrink the fuel",
+            self.divisor, self.offset
+        )
+    }
+}
+
+impl Error for FuelRuleError {}
+
+impl FuelRule {
+    // the fuel has to shrink every time, or adding fuel for the fuel never
+    // ends; a zero divisor can't be used at all
+    fn new(divisor: i64, offset: i64) -> Result<Self, FuelRuleError> {
+        if divisor < 1 || offset < 0 || (divisor, offset) == (1, 0) {
+            return Err(FuelRuleError { divisor, offset });
+        }
+
+        Ok(FuelRule { divisor, offset })
+    }
+
+    fn fuel_for(&self, mass: i64) -> i64 {
+        calculate_fuel_with(mass, self.divisor, self.offset)
+    }
+}
 
 struct FuelIter {
-    previous: i32,
+    previous: i64,
+    rule: FuelRule,
 }
 
 impl FuelIter {
-    fn new(previous: i32) -> Self {
-        FuelIter { previous }
+    fn new(previous: i64) -> Self {
+        Self::with_params(previous, 3, 2).expect("mass / 3 - 2 always shrinks")
+    }
+
+    fn with_params(previous: i64, divisor: i64, offset: i64) -> Result<Self, FuelRuleError> {
+        Ok(FuelIter {
+            previous,
+            rule: FuelRule::new(divisor, offset)?,
+        })
     }
 }
 
 impl std::iter::Iterator for FuelIter {
-    type Item = i32;
+    type Item = i64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let fuel = calculate_fuel(self.previous);
+        let fuel = self.rule.fuel_for(self.previous);
         self.previous = fuel;
 
         if fuel > 0 {
@@ -25,20 +74,20 @@ impl std::iter::Iterator for FuelIter {
     }
 }
 
-fn get_input() -> Result<Vec<i32>, Box<dyn Error>> {
+fn get_input() -> Result<Vec<i64>, Box<dyn Error>> {
     let result = fs::read_to_string("input.txt")?
         .lines()
         .map(|line| line.parse())
-        .collect::<Result<Vec<i32>, std::num::ParseIntError>>()?;
+        .collect::<Result<Vec<i64>, std::num::ParseIntError>>()?;
 
     Ok(result)
 }
 
-fn solve_1(input: &[i32]) -> i32 {
+fn solve_1(input: &[i64]) -> i64 {
     input.iter().map(|&mass| calculate_fuel(mass)).sum()
 }
 
-fn solve_2(input: &[i32]) -> i32 {
+fn solve_2(input: &[i64]) -> i64 {
     input.iter().map(|&mass| calculate_total_fuel(mass)).sum()
 }
 
@@ -52,10 +101,75 @@ fn main() {
     println!("second solution: {:?}", solve_2(&input));
 }
 
-fn calculate_total_fuel(start_mass: i32) -> i32 {
+fn calculate_total_fuel(start_mass: i64) -> i64 {
     FuelIter::new(start_mass).sum()
 }
 
-fn calculate_fuel(mass: i32) -> i32 {
-    max(mass / 3 - 2, 0)
+fn calculate_fuel(mass: i64) -> i64 {
+    calculate_fuel_with(mass, 3, 2)
+}
+
+// a single step, so any rule goes; only repeating it needs the fuel to shrink
+fn calculate_fuel_with(mass: i64, divisor: i64, offset: i64) -> i64 {
+    max(mass / divisor - offset, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuel_rules() {
+        assert_eq!(calculate_fuel_with(14, 3, 2), 2);
+        assert_eq!(calculate_fuel_with(14, 5, 1), 1);
+        assert_eq!(calculate_fuel_with(2, 3, 2), 0);
+        assert_eq!(calculate_fuel_with(14, 1, 1), 13);
+        assert_eq!(calculate_fuel_with(14, 1, 0), 14);
+        assert_eq!(calculate_fuel(1969), 654);
+    }
+
+    #[test]
+    fn reject_endless_fuel_rules() {
+        assert_eq!(
+            FuelRule::new(0, 2),
+            Err(FuelRuleError {
+                divisor: 0,
+                offset: 2
+            })
+        );
+        assert!(FuelIter::with_params(100, 1, 0).is_err());
+        assert!(FuelIter::with_params(100, -3, 2).is_err());
+        // 2 / 2 + 1 is 2 again
+        assert!(FuelIter::with_params(100, 2, -1).is_err());
+        assert_eq!(
+            FuelRuleError {
+                divisor: 1,
+                offset: 0
+            }
+            .to_string(),
+            "mass / 1 - 0 needs a positive divisor and has to shrink the fuel"
+        );
+    }
+
+    #[test]
+    fn sums_past_i32() {
+        let input = vec![i32::MAX as i64; 4];
+
+        // each module alone fits in an i32, but the total doesn't
+        assert_eq!(solve_1(&input), 4 * 715_827_880);
+        assert!(solve_1(&input) > i32::MAX as i64);
+        assert!(solve_2(&input) > solve_1(&input));
+    }
+
+    #[test]
+    fn total_fuel() {
+        assert_eq!(calculate_total_fuel(1969), 966);
+        assert_eq!(calculate_total_fuel(100756), 50346);
+        assert_eq!(
+            FuelIter::with_params(100, 2, 0)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![50, 25, 12, 6, 3, 1]
+        );
+    }
 }
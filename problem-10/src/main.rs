@@ -1,12 +1,16 @@
+use grid::Grid;
 use std::{
     cmp::{Ordering, PartialEq, PartialOrd},
     collections::{HashSet, VecDeque},
     error::Error,
-    fs,
-    hash::Hash,
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader},
     iter::Iterator,
+    path::Path,
 };
 
+/// `(x, y)`: the column, then the row, counting from the top left.
 #[derive(Clone, Copy, Debug)]
 struct Point(isize, isize, Space);
 
@@ -16,50 +20,39 @@ impl Point {
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug)]
-enum Quadrant {
-    One,
-    Two,
-    Three,
-    Four,
-}
-
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[derive(Clone, Copy, Debug)]
 struct Slope {
     slope: (isize, isize),
-    quadrant: Quadrant,
+    // clockwise from straight up, in radians within [0, 2π)
+    angle: f64,
 }
 
 impl Slope {
     fn new(origin: &Point, point: &Point) -> Self {
         let slope = get_slope(*origin, *point);
+        let (rise, run) = slope;
+        let angle = (run as f64)
+            .atan2(-rise as f64)
+            .rem_euclid(2.0 * std::f64::consts::PI);
 
-        let quadrant = match slope {
-            (rise, 0) => match rise {
-                rise if rise.is_negative() => Quadrant::One,
-                _ => Quadrant::Three,
-            },
-            (0, run) => match run {
-                run if run.is_positive() => Quadrant::Two,
-                _ => Quadrant::Four,
-            },
-            (rise, run) if run.is_positive() && rise.is_negative() => Quadrant::One,
-            (rise, run) if run.is_positive() && rise.is_positive() => Quadrant::Two,
-            (rise, run) if run.is_negative() && rise.is_positive() => Quadrant::Three,
-            (rise, run) if run.is_negative() && rise.is_negative() => Quadrant::Four,
-            _ => panic!("Could not determine quadrant for: {:?}", slope),
-        };
+        Slope { slope, angle }
+    }
+}
 
-        Slope { slope, quadrant }
+// Two slopes are equal when they point the same way from their origin.
+// Slopes are always reduced, so the (rise, run) pair alone identifies the
+// direction. The angle is derived from it, and as an f64 it can't be hashed.
+impl PartialEq for Slope {
+    fn eq(&self, other: &Self) -> bool {
+        self.slope == other.slope
     }
+}
 
-    // not a real angle, just used for comparison
-    fn angle(&self) -> f32 {
-        match self.slope {
-            (rise, 0) if rise.is_positive() => std::f32::INFINITY,
-            (rise, 0) if rise.is_negative() => std::f32::NEG_INFINITY,
-            (rise, run) => rise as f32 / run as f32,
-        }
+impl Eq for Slope {}
+
+impl Hash for Slope {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.slope.hash(state);
     }
 }
 
@@ -77,16 +70,13 @@ impl PartialOrd for Point {
 
 impl PartialOrd for Slope {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match self.quadrant.cmp(&other.quadrant) {
-            Ordering::Equal => self.angle().partial_cmp(&other.angle()),
-            x => Some(x),
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Slope {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+        self.angle.total_cmp(&other.angle)
     }
 }
 
@@ -107,21 +97,23 @@ impl Space {
     }
 }
 
+// the largest x and y on the map
 fn get_bounds(map: &[Point]) -> (isize, isize) {
-    map.iter().fold((0, 0), |(row, col), point| {
-        let row = if point.0 > row { point.0 } else { row };
-        let col = if point.1 > col { point.1 } else { col };
-        (row, col)
+    map.iter().fold((0, 0), |(x, y), point| {
+        let x = if point.0 > x { point.0 } else { x };
+        let y = if point.1 > y { point.1 } else { y };
+        (x, y)
     })
 }
 
-fn index_map(map: &[Point]) -> Vec<Vec<Space>> {
-    let (last_row, last_col) = get_bounds(map);
-    let indexed_map = vec![vec![Empty; last_col as usize + 1]; last_row as usize + 1];
+// the grid is indexed by row, so a point is found at `[(y, x)]`
+fn index_map(map: &[Point]) -> Grid<Space> {
+    let (last_x, last_y) = get_bounds(map);
+    let indexed_map = Grid::new(last_x as usize + 1, last_y as usize + 1, Empty);
 
     map.iter()
-        .fold(indexed_map, |mut indexed_map, Point(row, col, space)| {
-            indexed_map[*row as usize][*col as usize] = *space;
+        .fold(indexed_map, |mut indexed_map, Point(x, y, space)| {
+            indexed_map[(*y as usize, *x as usize)] = *space;
             indexed_map
         })
 }
@@ -132,13 +124,13 @@ fn get_asteroids(map: &[Point]) -> Vec<&Point> {
         .collect()
 }
 
-fn has_clear_path(map: &[Vec<Space>], a: Point, b: Point) -> bool {
+fn has_clear_path(map: &Grid<Space>, a: Point, b: Point) -> bool {
     let (a, b) = order(a, b);
     let (rise, run) = get_slope(a, b);
     let (mut x, mut y) = (a.0 + run, a.1 + rise);
 
     while Point(x, y, Empty) < b {
-        if map[x as usize][y as usize] == Asteroid {
+        if map[(y as usize, x as usize)] == Asteroid {
             return false;
         }
         y += rise;
@@ -149,15 +141,15 @@ fn has_clear_path(map: &[Vec<Space>], a: Point, b: Point) -> bool {
 }
 
 fn destroy_asteroid(
-    map: &mut [Vec<Space>],
+    map: &mut Grid<Space>,
     origin: &Point,
     slope: &Slope,
 ) -> Option<(isize, isize)> {
     let (rise, run) = slope.slope;
     let (mut x, mut y) = (origin.0 + run, origin.1 + rise);
 
-    while x < map[0].len() as isize && y < map[0].len() as isize {
-        let loc = &mut map[x as usize][y as usize];
+    while x >= 0 && y >= 0 {
+        let loc = map.get_mut(y as usize, x as usize)?;
 
         if *loc == Asteroid {
             *loc = Empty;
@@ -185,31 +177,30 @@ fn get_slope(a: Point, b: Point) -> (isize, isize) {
     (rise / gcd, run / gcd)
 }
 
-fn count_asteroids_in_sight(asteroids: &[&Point], asteroid: &Point, map: &[Vec<Space>]) -> usize {
-    asteroids
-        .iter()
-        .filter(|&&point| point != asteroid)
-        .filter(|&&&other| has_clear_path(&map, *asteroid, other))
-        .count()
-}
-
 #[derive(Debug)]
 struct Spinner(VecDeque<Slope>);
 
+impl Spinner {
+    /// Rotates `slopes` so the spin begins at `start`, or `None` if `start`
+    /// isn't one of them.
+    fn starting_from(mut slopes: VecDeque<Slope>, start: Slope) -> Option<Self> {
+        let position = slopes.iter().position(|&slope| slope == start)?;
+        slopes.rotate_left(position);
+
+        Some(Spinner(slopes))
+    }
+}
+
 impl Iterator for Spinner {
     type Item = Slope;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.0.pop_front();
-        self.0.push_back(next.unwrap());
-        next
+        let next = self.0.pop_front()?;
+        self.0.push_back(next);
+        Some(next)
     }
 }
 
-fn get_divisors_iter(x: isize) -> impl DoubleEndedIterator<Item = isize> {
-    (1..=x).filter(move |i| x % i == 0)
-}
-
 fn order<T: PartialOrd>(a: T, b: T) -> (T, T) {
     if a > b {
         (b, a)
@@ -218,14 +209,12 @@ fn order<T: PartialOrd>(a: T, b: T) -> (T, T) {
     }
 }
 
-fn gcd(a: isize, b: isize) -> isize {
-    let (a, b) = order(a, b);
-    get_divisors_iter(a.abs())
-        .rev()
-        .skip_while(|divisor| b % divisor != 0)
-        .take(1)
-        .next()
-        .unwrap()
+fn gcd(mut a: isize, mut b: isize) -> isize {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a.abs()
 }
 
 fn full_dedup<T: Eq + Hash + Copy>(vec: Vec<T>) -> Vec<T> {
@@ -233,56 +222,126 @@ fn full_dedup<T: Eq + Hash + Copy>(vec: Vec<T>) -> Vec<T> {
     vec.into_iter().filter(|x| set.insert(*x)).collect()
 }
 
-fn solve_1(map: &[Point]) -> usize {
-    let asteroids = get_asteroids(map);
-    let map = index_map(map);
+struct AsteroidMap {
+    points: Vec<Point>,
+    indexed: Grid<Space>,
+}
 
-    asteroids
-        .iter()
-        .map(|asteroid| count_asteroids_in_sight(&asteroids, asteroid, &map))
-        .max()
-        .unwrap()
+impl AsteroidMap {
+    fn new(points: Vec<Point>) -> Self {
+        let indexed = index_map(&points);
+
+        AsteroidMap { points, indexed }
+    }
+
+    fn asteroids(&self) -> Vec<&Point> {
+        get_asteroids(&self.points)
+    }
+
+    fn has_clear_path(&self, a: Point, b: Point) -> bool {
+        has_clear_path(&self.indexed, a, b)
+    }
+
+    /// How many other asteroids each asteroid can see, in scan order.
+    fn visibility_map(&self) -> Vec<(Point, usize)> {
+        let asteroids = self.asteroids();
+
+        asteroids
+            .iter()
+            .map(|&asteroid| {
+                let in_sight = asteroids
+                    .iter()
+                    .filter(|&&other| other != asteroid)
+                    .filter(|&&&other| self.has_clear_path(*asteroid, other))
+                    .count();
+                (*asteroid, in_sight)
+            })
+            .collect()
+    }
+
+    /// The asteroid that can see the most others, and how many it sees.
+    /// Ties go to the lowest row, then the lowest column.
+    fn best_station(&self) -> (Point, usize) {
+        self.visibility_map()
+            .into_iter()
+            .max_by(|(a, a_count), (b, b_count)| {
+                a_count.cmp(b_count).then((b.1, b.0).cmp(&(a.1, a.0)))
+            })
+            .unwrap()
+    }
 }
 
-fn solve_2(map: &[Point]) -> isize {
-    let asteroids = get_asteroids(map);
-    let mut map = index_map(map);
+struct VaporizationIter<'a> {
+    map: &'a mut Grid<Space>,
+    origin: Point,
+    spinner: Spinner,
+}
 
-    let (start, _) = asteroids
-        .iter()
-        .map(|asteroid| {
-            (
-                asteroid,
-                count_asteroids_in_sight(&asteroids, asteroid, &map),
-            )
-        })
-        .max_by(|(_, a), (_, b)| a.cmp(b))
-        .unwrap();
+impl<'a> VaporizationIter<'a> {
+    fn new(map: &'a mut Grid<Space>, origin: Point) -> Self {
+        let mut slopes: Vec<_> = map
+            .rows()
+            .enumerate()
+            .flat_map(|(row, spaces)| {
+                spaces
+                    .iter()
+                    .enumerate()
+                    .map(move |(col, &space)| Point(col as isize, row as isize, space))
+            })
+            .filter(|point| point.2 == Asteroid && *point != origin)
+            .map(|asteroid| Slope::new(&origin, &asteroid))
+            .collect();
 
-    let mut slopes: Vec<_> = asteroids
-        .iter()
-        .map(|asteroid| Slope::new(start, asteroid))
-        .collect();
+        // the laser starts pointing straight up, even if nothing is there; an
+        // empty slope is dropped the first time round
+        let north = Slope::new(&origin, &Point(origin.0, origin.1 - 1, Empty));
+        slopes.push(north);
+        slopes.sort();
 
-    slopes.sort();
+        let slopes = full_dedup(slopes).into_iter().collect();
+        let spinner = Spinner::starting_from(slopes, north).expect("north was just added");
 
-    let mut slopes = Spinner(full_dedup(slopes).into_iter().collect());
+        VaporizationIter {
+            map,
+            origin,
+            spinner,
+        }
+    }
+}
 
-    let mut first = slopes.next().unwrap();
+impl Iterator for VaporizationIter<'_> {
+    type Item = (isize, isize);
 
-    while first.slope != (-1, 0) {
-        first = slopes.next().unwrap()
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let slope = self.spinner.next()?;
+
+            match destroy_asteroid(self.map, &self.origin, &slope) {
+                Some(point) => return Some(point),
+                // nothing left along this slope, so drop it from the rotation
+                None => {
+                    self.spinner.0.pop_back();
+                }
+            }
+        }
     }
+}
 
-    Some(first)
+fn solve_1(map: &AsteroidMap) -> usize {
+    map.visibility_map()
         .into_iter()
-        .chain(slopes)
-        .map(|point| destroy_asteroid(&mut map, start, &point))
-        .filter(|result| result.is_some())
-        .take(200)
-        .last()
-        .flatten()
-        .map(|point| 100 * point.0 + point.1)
+        .map(|(_, count)| count)
+        .max()
+        .unwrap()
+}
+
+fn solve_2(map: &AsteroidMap) -> isize {
+    let (start, _) = map.best_station();
+    let mut indexed = map.indexed.clone();
+
+    VaporizationIter::new(&mut indexed, start)
+        .nth(199)
+        .map(|(x, y)| 100 * x + y)
         .expect("should have found an answer!")
 }
 
@@ -292,30 +351,42 @@ fn main() {
         std::process::exit(1);
     });
 
-    let input = parse_input(&input);
+    let input = AsteroidMap::new(input);
 
     println!("first solution: {:?}", solve_1(&input));
     println!("second solution: {:?}", solve_2(&input));
 }
 
-fn parse_input(input: &[String]) -> Vec<Point> {
-    input
-        .iter()
+fn parse_map(raw: &str) -> Vec<Point> {
+    raw.trim()
+        .lines()
         .enumerate()
-        .flat_map(|(col, raw)| {
+        .flat_map(|(y, raw)| {
             raw.chars()
                 .enumerate()
-                .map(move |(row, char)| Point(row as isize, col as isize, Space::parse(char)))
+                .map(move |(x, char)| Point(x as isize, y as isize, Space::parse(char)))
         })
         .collect()
 }
 
-fn get_input() -> Result<Vec<String>, Box<dyn Error>> {
-    Ok(fs::read_to_string("input.txt")?
-        .trim()
-        .lines()
-        .map(|str| str.to_string())
-        .collect::<Vec<_>>())
+fn read_input_from<R: BufRead>(mut reader: R) -> Result<Vec<Point>, Box<dyn Error>> {
+    let mut raw = String::new();
+    reader.read_to_string(&mut raw)?;
+
+    Ok(parse_map(&raw))
+}
+
+fn parse_map_from_file(path: &str) -> Result<Vec<Point>, Box<dyn Error>> {
+    read_input_from(BufReader::new(File::open(path)?))
+}
+
+/// Reads `input.txt` if there is one, otherwise stdin.
+fn get_input() -> Result<Vec<Point>, Box<dyn Error>> {
+    if Path::new("input.txt").exists() {
+        parse_map_from_file("input.txt")
+    } else {
+        read_input_from(io::stdin().lock())
+    }
 }
 
 #[cfg(test)]
@@ -325,57 +396,89 @@ mod tests {
 
     #[test]
     fn get_bounds_from_map() {
-        let max_row = 5;
-        let max_col = 4;
-        let input: Vec<_> = (0..=max_row)
-            .flat_map(|row| (0..=max_col).map(move |col| Point(row, col, Space::Empty)))
+        let max_x = 5;
+        let max_y = 4;
+        let input: Vec<_> = (0..=max_y)
+            .flat_map(|y| (0..=max_x).map(move |x| Point(x, y, Space::Empty)))
             .collect();
 
-        let (row_bound, col_bound) = get_bounds(&input);
+        let (x_bound, y_bound) = get_bounds(&input);
 
-        assert_eq!(row_bound, max_row);
-        assert_eq!(col_bound, max_col);
+        assert_eq!(x_bound, max_x);
+        assert_eq!(y_bound, max_y);
+        assert_eq!(
+            (index_map(&input).width(), index_map(&input).height()),
+            (6, 5)
+        );
     }
 
     #[test]
     fn adjacent_clear_path() {
-        let map = vec![vec![Asteroid, Empty], vec![Empty, Asteroid]];
+        let map = Grid::from(vec![vec![Asteroid, Empty], vec![Empty, Asteroid]]);
 
         let a = Point(0, 0, Asteroid);
         let b = Point(1, 1, Asteroid);
 
-        assert_eq!(has_clear_path(&map, a, b), true);
+        assert!(has_clear_path(&map, a, b));
     }
 
     #[test]
     fn blocked_and_free() {
-        let map = vec![
+        let map = Grid::from(vec![
             vec![Asteroid, Empty, Empty],
             vec![Empty, Asteroid, Empty],
             vec![Empty, Asteroid, Asteroid],
-        ];
+        ]);
 
         let a = Point(0, 0, Asteroid);
         let b = Point(2, 2, Asteroid);
-        let c = Point(2, 1, Asteroid);
+        let c = Point(1, 2, Asteroid);
 
-        assert_eq!(has_clear_path(&map, a, b), false);
-        assert_eq!(has_clear_path(&map, a, c), true);
+        assert!(!has_clear_path(&map, a, b));
+        assert!(has_clear_path(&map, a, c));
     }
 
     #[test]
-    fn same_x() {
-        let map = parse_input(&[
-            ".#..#".to_string(),
-            ".....".to_string(),
-            "#####".to_string(),
-            "....#".to_string(),
-            "...##".to_string(),
+    fn destroy_on_non_square_map() {
+        let mut map = Grid::from(vec![
+            vec![Empty, Empty, Empty, Empty, Asteroid],
+            vec![Empty, Empty, Empty, Empty, Empty],
+            vec![Asteroid, Empty, Empty, Empty, Empty],
         ]);
+        let origin = Point(0, 2, Asteroid);
+        let slope = Slope::new(&origin, &Point(4, 0, Asteroid));
+
+        assert_eq!(destroy_asteroid(&mut map, &origin, &slope), Some((4, 0)));
+        assert_eq!(map[(0, 4)], Empty);
+        assert_eq!(destroy_asteroid(&mut map, &origin, &slope), None);
+    }
+
+    #[test]
+    fn destroy_on_tall_map() {
+        let mut map = Grid::new(2, 6, Empty);
+        map[(5, 0)] = Asteroid;
+        let origin = Point(0, 0, Asteroid);
+        let slope = Slope::new(&origin, &Point(0, 5, Asteroid));
+
+        assert_eq!(destroy_asteroid(&mut map, &origin, &slope), Some((0, 5)));
+    }
+
+    #[test]
+    fn destroy_off_the_left_edge() {
+        let mut map = Grid::from(vec![vec![Asteroid, Asteroid], vec![Asteroid, Empty]]);
+        let origin = Point(0, 1, Asteroid);
+        let slope = Slope::new(&origin, &Point(-1, 1, Empty));
+
+        assert_eq!(destroy_asteroid(&mut map, &origin, &slope), None);
+    }
+
+    #[test]
+    fn same_x() {
+        let map = parse_map(".#..#\n.....\n#####\n....#\n...##");
         let asteroid = Point(4, 0, Asteroid);
         let map = index_map(&map);
 
-        assert_eq!(has_clear_path(&map, asteroid, Point(4, 4, Asteroid)), false);
+        assert!(!has_clear_path(&map, asteroid, Point(4, 4, Asteroid)));
     }
 
     #[test]
@@ -388,22 +491,22 @@ mod tests {
 
         let slope_one = Slope::new(&start, &one);
 
-        assert_eq!(slope_one.quadrant, Quadrant::One);
+        assert!((0.0..90.0).contains(&slope_one.angle.to_degrees()));
         assert_eq!(slope_one.slope, (-1, 1));
 
         let slope_two = Slope::new(&start, &two);
 
-        assert_eq!(slope_two.quadrant, Quadrant::Two);
+        assert!((90.0..180.0).contains(&slope_two.angle.to_degrees()));
         assert_eq!(slope_two.slope, (1, 1));
 
         let slope_three = Slope::new(&start, &three);
 
-        assert_eq!(slope_three.quadrant, Quadrant::Three);
+        assert!((180.0..270.0).contains(&slope_three.angle.to_degrees()));
         assert_eq!(slope_three.slope, (1, -1));
 
         let slope_four = Slope::new(&start, &four);
 
-        assert_eq!(slope_four.quadrant, Quadrant::Four);
+        assert!((270.0..360.0).contains(&slope_four.angle.to_degrees()));
         assert_eq!(slope_four.slope, (-1, -1));
 
         assert_eq!(slope_one.cmp(&slope_two), Ordering::Less);
@@ -427,16 +530,226 @@ mod tests {
         let left = Slope::new(&start, &Point(3, 4, Empty));
 
         assert_eq!(up.slope, (-1, 0));
-        assert_eq!(up.quadrant, Quadrant::One);
+        assert!((0.0..90.0).contains(&up.angle.to_degrees()));
         assert_eq!(right.slope, (0, 1));
-        assert_eq!(right.quadrant, Quadrant::Two);
+        assert!((90.0..180.0).contains(&right.angle.to_degrees()));
         assert_eq!(down.slope, (1, 0));
-        assert_eq!(down.quadrant, Quadrant::Three);
+        assert!((180.0..270.0).contains(&down.angle.to_degrees()));
         assert_eq!(left.slope, (0, -1));
-        assert_eq!(left.quadrant, Quadrant::Four);
+        assert!((270.0..360.0).contains(&left.angle.to_degrees()));
 
         assert_eq!(up.cmp(&right), Ordering::Less);
         assert_eq!(right.cmp(&down), Ordering::Less);
         assert_eq!(down.cmp(&left), Ordering::Less);
     }
+
+    #[test]
+    fn compass_angles() {
+        let start = Point(4, 4, Empty);
+        let degrees = |x, y| Slope::new(&start, &Point(x, y, Empty)).angle.to_degrees();
+
+        assert_eq!(degrees(4, 0), 0.0);
+        assert_eq!(degrees(8, 4), 90.0);
+        assert_eq!(degrees(4, 9), 180.0);
+        assert_eq!(degrees(0, 4), 270.0);
+        assert_eq!(degrees(5, 3), 45.0);
+    }
+
+    #[test]
+    fn angles_sort_like_slopes() {
+        let start = Point(4, 4, Empty);
+        let mut slopes: Vec<_> = (0..9)
+            .flat_map(|x| (0..9).map(move |y| Point(x, y, Empty)))
+            .filter(|point| *point != start)
+            .map(|point| Slope::new(&start, &point))
+            .collect();
+        slopes = full_dedup(slopes);
+
+        let mut by_angle = slopes.clone();
+        slopes.sort();
+        by_angle.sort_by(|a, b| a.angle.partial_cmp(&b.angle).unwrap());
+
+        assert_eq!(slopes, by_angle);
+    }
+
+    #[test]
+    fn same_direction_from_different_origins() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |slope: &Slope| {
+            let mut hasher = DefaultHasher::new();
+            slope.hash(&mut hasher);
+            hasher.finish()
+        };
+        let near = Slope::new(&Point(0, 0, Empty), &Point(1, 2, Empty));
+        let far = Slope::new(&Point(5, 5, Empty), &Point(8, 11, Empty));
+        let other = Slope::new(&Point(0, 0, Empty), &Point(2, 1, Empty));
+
+        assert_eq!(near, far);
+        assert_eq!(hash(&near), hash(&far));
+        assert_ne!(near, other);
+        assert_eq!(full_dedup(vec![near, other, far]), vec![near, other]);
+    }
+
+    #[test]
+    fn euclidean_gcd() {
+        assert_eq!(gcd(1071, 462), 21);
+        assert_eq!(gcd(12, 8), 4);
+        assert_eq!(gcd(-4, 6), 2);
+        assert_eq!(gcd(3, -1), 1);
+
+        [(12, 8), (1071, 462), (-4, 6), (7, 13), (9, 0), (0, 5)]
+            .iter()
+            .for_each(|&(a, b)| {
+                assert_eq!(gcd(a, b), gcd(b, a));
+                assert_eq!(gcd(a, 0), a.abs());
+            });
+    }
+
+    #[test]
+    fn reduced_slopes() {
+        let origin = Point(2, 2, Asteroid);
+
+        assert_eq!(get_slope(origin, Point(6, 4, Asteroid)), (1, 2));
+        assert_eq!(get_slope(origin, Point(-1, 8, Asteroid)), (2, -1));
+        assert_eq!(get_slope(origin, Point(0, -4, Asteroid)), (-3, -1));
+        assert_eq!(get_slope(origin, Point(2, 7, Asteroid)), (1, 0));
+    }
+
+    fn vaporize(raw: &str) -> Vec<(isize, isize)> {
+        let map = AsteroidMap::new(parse_map(raw));
+        let (start, _) = map.best_station();
+        let mut indexed = map.indexed.clone();
+
+        VaporizationIter::new(&mut indexed, start).collect()
+    }
+
+    #[test]
+    fn read_map_from_reader() {
+        let raw = ".#.\n#..\n";
+
+        assert_eq!(read_input_from(raw.as_bytes()).unwrap(), parse_map(raw));
+    }
+
+    #[test]
+    fn parse_asteroid_map() {
+        let map = AsteroidMap::new(parse_map(".#.\n#.."));
+
+        assert_eq!(map.points.len(), 6);
+        assert_eq!((map.indexed.height(), map.indexed.width()), (2, 3));
+        assert_eq!(map.indexed, Grid::from_char_map(".#.\n#..", Space::parse));
+        assert_eq!(
+            map.asteroids(),
+            vec![&Point(1, 0, Asteroid), &Point(0, 1, Asteroid)]
+        );
+        assert!(map.has_clear_path(Point(1, 0, Asteroid), Point(0, 1, Asteroid)));
+        assert_eq!(map.best_station().1, 1);
+    }
+
+    #[test]
+    fn best_station_on_small_map() {
+        let map = AsteroidMap::new(parse_map(".#..#\n.....\n#####\n....#\n...##"));
+        let (station, in_sight) = map.best_station();
+
+        assert_eq!((station.0, station.1, in_sight), (3, 4, 8));
+    }
+
+    #[test]
+    fn visibility_of_every_asteroid() {
+        let map = AsteroidMap::new(parse_map(".#..#\n.....\n#####\n....#\n...##"));
+        let visibility = map.visibility_map();
+
+        assert_eq!(visibility.len(), map.asteroids().len());
+        assert_eq!(visibility[0], (Point(1, 0, Asteroid), 7));
+        assert_eq!(
+            visibility
+                .iter()
+                .map(|(_, count)| *count)
+                .collect::<Vec<_>>(),
+            vec![7, 7, 6, 7, 7, 7, 5, 7, 8, 7]
+        );
+        assert_eq!(
+            visibility.iter().map(|(_, count)| *count).max(),
+            Some(solve_1(&map))
+        );
+    }
+
+    #[test]
+    fn best_station_ties() {
+        let map = AsteroidMap::new(parse_map("#.#"));
+
+        assert_eq!(map.best_station().0, Point(0, 0, Asteroid));
+
+        // the top row wins, even though its asteroid is further right
+        let map = AsteroidMap::new(parse_map(".#\n#."));
+
+        assert_eq!(map.best_station().0, Point(1, 0, Asteroid));
+    }
+
+    #[test]
+    fn empty_spinner() {
+        assert_eq!(Spinner(VecDeque::new()).next(), None);
+    }
+
+    #[test]
+    fn spinner_from_south() {
+        let origin = Point(1, 1, Empty);
+        let towards = |x, y| Slope::new(&origin, &Point(x, y, Empty));
+        let (north, east, south, west) =
+            (towards(1, 0), towards(2, 1), towards(1, 2), towards(0, 1));
+        let slopes: VecDeque<_> = vec![north, east, south, west].into_iter().collect();
+
+        let mut spinner = Spinner::starting_from(slopes.clone(), south).unwrap();
+
+        assert_eq!(spinner.next(), Some(south));
+        assert_eq!(spinner.next(), Some(west));
+        assert_eq!(spinner.next(), Some(north));
+        assert!(Spinner::starting_from(slopes, towards(2, 2)).is_none());
+    }
+
+    #[test]
+    fn vaporize_lone_asteroid() {
+        assert_eq!(vaporize("...\n.#.\n..."), vec![]);
+    }
+
+    #[test]
+    fn vaporize_small_map() {
+        let order = vaporize(".#..#\n.....\n#####\n....#\n...##");
+
+        assert_eq!(order.len(), 9);
+        assert_eq!(order[..3], [(3, 2), (4, 0), (4, 2)]);
+    }
+
+    #[test]
+    fn vaporize_large_example() {
+        let order = vaporize(
+            ".#..##.###...#######\n\
+             ##.############..##.\n\
+             .#.######.########.#\n\
+             .###.#######.####.#.\n\
+             #####.##.#.##.###.##\n\
+             ..#####..#.#########\n\
+             ####################\n\
+             #.####....###.#.#.##\n\
+             ##.#################\n\
+             #####.##.###..####..\n\
+             ..######..##.#######\n\
+             ####.##.####...##..#\n\
+             .#####..#.######.###\n\
+             ##...#.##########...\n\
+             #.##########.#######\n\
+             .####.#.###.###.#.##\n\
+             ....##.##.###..#####\n\
+             .#.#.###########.###\n\
+             #.#.#.#####.####.###\n\
+             ###.##.####.##.#..##",
+        );
+
+        assert_eq!(order[0], (11, 12));
+        assert_eq!(order[1], (12, 1));
+        assert_eq!(order[19], (16, 0));
+        assert_eq!(order[199], (8, 2));
+        assert_eq!(order.len(), 299);
+        assert_eq!(order[298], (11, 1));
+    }
 }
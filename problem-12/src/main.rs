@@ -1,6 +1,4 @@
-use std::{error::Error, fs};
-
-use regex::Regex;
+use std::{error::Error, fmt, fs, thread};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 struct Vec3 {
@@ -55,6 +53,39 @@ impl std::ops::AddAssign for Vec3 {
     }
 }
 
+impl std::ops::Sub for Vec3 {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self::Output {
+        Vec3 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl std::ops::Neg for Vec3 {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Vec3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl std::ops::Mul<i32> for Vec3 {
+    type Output = Self;
+    fn mul(self, scalar: i32) -> Self::Output {
+        Vec3 {
+            x: self.x * scalar,
+            y: self.y * scalar,
+            z: self.z * scalar,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 struct Moon {
     position: Vec3,
@@ -69,10 +100,6 @@ impl Moon {
         }
     }
 
-    fn apply_gravity(&mut self, other: &Self) {
-        self.velocity += self.position.apply_gravity(&other.position)
-    }
-
     fn step(&mut self) {
         self.position += self.velocity;
     }
@@ -98,146 +125,389 @@ fn main() {
         std::process::exit(1);
     });
 
-    let input = parse_input(input);
+    let input: Vec<Moon> = input.into_iter().map(Moon::new).collect();
 
     println!("first solution: {:?}", solve_1(input.clone(), 1000));
     println!("second solution: {:?}", solve_2(input));
 }
 
-fn solve_1(mut moons: Vec<Moon>, steps: usize) -> i32 {
-    (0..steps).for_each(|_| {
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moons
-                .iter()
-                .for_each(|other_moon| moon.apply_gravity(other_moon));
-            moons.insert(i, moon)
+// gravity is simultaneous, so every delta is worked out before any is applied
+fn apply_all_gravity(moons: &mut [Moon]) {
+    let n = moons.len();
+    let mut deltas = vec![Vec3::zero(); n];
+
+    (0..n)
+        .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+        .for_each(|(i, j)| {
+            let delta = moons[i].position.apply_gravity(&moons[j].position);
+            deltas[i] += delta;
+            deltas[j] += -delta;
         });
 
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moon.step();
-            moons.insert(i, moon)
-        });
-    });
+    moons
+        .iter_mut()
+        .zip(deltas)
+        .for_each(|(moon, delta)| moon.velocity += delta);
+}
 
-    moons.iter().fold(0, |total_energy, moon| {
-        total_energy + moon.get_total_energy()
-    })
+fn step_moons(moons: &mut [Moon]) {
+    apply_all_gravity(moons);
+    moons.iter_mut().for_each(Moon::step);
 }
 
-fn solve_2(mut moons: Vec<Moon>) -> usize {
-    let initial = moons.clone();
-    let mut count = 0;
+// yields the state of every moon after each tick
+struct SimulationIter {
+    moons: Vec<Moon>,
+}
 
-    let x = loop {
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moons
-                .iter()
-                .for_each(|other_moon| moon.apply_gravity(other_moon));
-            moons.insert(i, moon)
-        });
+impl SimulationIter {
+    fn new(moons: Vec<Moon>) -> Self {
+        SimulationIter { moons }
+    }
+}
 
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moon.step();
-            moons.insert(i, moon)
-        });
+impl Iterator for SimulationIter {
+    type Item = Vec<Moon>;
 
-        let is_same = moons
-            .iter()
-            .zip(initial.iter())
-            .all(|(a, b)| a.position.x == b.position.x && a.velocity.x == b.velocity.x);
+    fn next(&mut self) -> Option<Self::Item> {
+        step_moons(&mut self.moons);
+        Some(self.moons.clone())
+    }
+}
 
-        count += 1;
+fn total_energy(moons: &[Moon]) -> i32 {
+    moons.iter().map(Moon::get_total_energy).sum()
+}
 
-        if is_same {
-            break count;
-        }
+fn solve_1(moons: Vec<Moon>, steps: usize) -> i32 {
+    let moons = match steps {
+        0 => moons,
+        steps => SimulationIter::new(moons).nth(steps - 1).unwrap(),
     };
 
-    let mut moons = initial.clone();
-    let mut count = 0;
+    total_energy(&moons)
+}
 
-    let y = loop {
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moons
-                .iter()
-                .for_each(|other_moon| moon.apply_gravity(other_moon));
-            moons.insert(i, moon)
-        });
+#[derive(Clone, Copy, Debug)]
+enum Axis {
+    X,
+    Y,
+    Z,
+}
 
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moon.step();
-            moons.insert(i, moon)
-        });
+impl Axis {
+    fn state(self, moon: &Moon) -> (i32, i32) {
+        match self {
+            Axis::X => (moon.position.x, moon.velocity.x),
+            Axis::Y => (moon.position.y, moon.velocity.y),
+            Axis::Z => (moon.position.z, moon.velocity.z),
+        }
+    }
+}
 
-        let is_same = moons
-            .iter()
-            .zip(initial.iter())
-            .all(|(a, b)| a.position.y == b.position.y && a.velocity.y == b.velocity.y);
+// axes don't affect each other, so each one repeats on its own schedule
+fn axis_period(moons: &[Moon], axis: Axis) -> usize {
+    let initial = moons
+        .iter()
+        .map(|moon| axis.state(moon))
+        .collect::<Vec<_>>();
+    let mut moons = moons.to_vec();
+    let mut count = 0;
 
+    loop {
+        step_moons(&mut moons);
         count += 1;
 
-        if is_same {
+        if moons
+            .iter()
+            .map(|moon| axis.state(moon))
+            .eq(initial.iter().copied())
+        {
             break count;
         }
-    };
+    }
+}
 
-    let mut moons = initial.clone();
-    let mut count = 0;
+// each axis is searched on its own thread
+fn axis_periods(moons: &[Moon]) -> (usize, usize, usize) {
+    thread::scope(|scope| {
+        let x = scope.spawn(|| axis_period(moons, Axis::X));
+        let y = scope.spawn(|| axis_period(moons, Axis::Y));
+        let z = axis_period(moons, Axis::Z);
 
-    let z = loop {
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moons
-                .iter()
-                .for_each(|other_moon| moon.apply_gravity(other_moon));
-            moons.insert(i, moon)
-        });
+        (x.join().unwrap(), y.join().unwrap(), z)
+    })
+}
 
-        (0..4).for_each(|i| {
-            let mut moon = moons.remove(i);
-            moon.step();
-            moons.insert(i, moon)
-        });
+fn solve_2(moons: Vec<Moon>) -> usize {
+    let (x, y, z) = axis_periods(&moons);
 
-        let is_same = moons
-            .iter()
-            .zip(initial.iter())
-            .all(|(a, b)| a.position.z == b.position.z && a.velocity.z == b.velocity.z);
+    lcm(lcm(x, y), z)
+}
 
-        count += 1;
+fn gcd_usize(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd_usize(b, a % b)
+    }
+}
 
-        if is_same {
-            break count;
+// divide first so the product doesn't overflow
+fn lcm(a: usize, b: usize) -> usize {
+    a / gcd_usize(a, b) * b
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    line: usize,
+    text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: can't parse {:?}", self.line, self.text)
+    }
+}
+
+impl Error for ParseError {}
+
+// expects `<x=1, y=-2, z=3>`
+fn parse_vec3(line: &str) -> Option<Vec3> {
+    let inner = line.trim().strip_prefix('<')?.strip_suffix('>')?;
+    let fields = inner.split(',').map(str::trim).collect::<Vec<_>>();
+
+    if fields.len() != 3 {
+        return None;
+    }
+
+    let value = |i: usize, name: &str| {
+        let (key, value) = fields[i].split_once('=')?;
+        if key == name {
+            value.parse().ok()
+        } else {
+            None
         }
     };
 
-    x / 2 * y / 2 * z / 2
+    Some(Vec3 {
+        x: value(0, "x")?,
+        y: value(1, "y")?,
+        z: value(2, "z")?,
+    })
 }
 
-fn parse_input(numbers: Vec<isize>) -> Vec<Moon> {
-    numbers
-        .chunks(3)
-        .map(|chunk| Vec3 {
-            x: chunk[0] as i32,
-            y: chunk[1] as i32,
-            z: chunk[2] as i32,
+fn parse_positions(text: &str) -> Result<Vec<Vec3>, ParseError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            parse_vec3(line).ok_or_else(|| ParseError {
+                line: i + 1,
+                text: line.to_string(),
+            })
         })
-        .map(Moon::new)
         .collect()
 }
 
-fn get_input() -> Result<Vec<isize>, Box<dyn Error>> {
-    let re = Regex::new(r"^<x=(.*), y=(.*), z=(.*)>$").unwrap();
-    Ok(fs::read_to_string("input.txt")?
-        .lines()
-        .flat_map(|line| re.captures_iter(line))
-        .flat_map(|x| (1..=3).map(move |i| x.get(i).unwrap().as_str()))
-        .map(|x| x.parse())
-        .collect::<Result<Vec<isize>, std::num::ParseIntError>>()?)
+fn get_input() -> Result<Vec<Vec3>, Box<dyn Error>> {
+    Ok(parse_positions(&fs::read_to_string("input.txt")?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_input(numbers: Vec<isize>) -> Vec<Moon> {
+        numbers
+            .chunks_exact(3)
+            .map(|chunk| Vec3 {
+                x: chunk[0] as i32,
+                y: chunk[1] as i32,
+                z: chunk[2] as i32,
+            })
+            .map(Moon::new)
+            .collect()
+    }
+
+    #[test]
+    fn parse_positions_test() {
+        let positions = parse_positions("<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>\n");
+
+        assert_eq!(
+            positions,
+            Ok(vec![
+                Vec3 { x: -1, y: 0, z: 2 },
+                Vec3 {
+                    x: 2,
+                    y: -10,
+                    z: -7
+                }
+            ])
+        );
+        assert_eq!(
+            parse_positions("<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>\n<x=4, y=-8>"),
+            Err(ParseError {
+                line: 3,
+                text: "<x=4, y=-8>".to_string()
+            })
+        );
+        assert!(parse_positions("<x=1, z=2, y=3>").is_err());
+    }
+
+    #[test]
+    fn ops_test() {
+        let a = Vec3 { x: 1, y: 2, z: 3 };
+        let b = Vec3 { x: 4, y: 5, z: 6 };
+
+        assert_eq!(b - a, Vec3 { x: 3, y: 3, z: 3 });
+        assert_eq!(
+            -a,
+            Vec3 {
+                x: -1,
+                y: -2,
+                z: -3
+            }
+        );
+        assert_eq!(a * 2, Vec3 { x: 2, y: 4, z: 6 });
+        assert_eq!(
+            b * 3,
+            Vec3 {
+                x: 12,
+                y: 15,
+                z: 18
+            }
+        );
+    }
+
+    #[test]
+    fn apply_all_gravity_test() {
+        let mut moons = parse_input(vec![-1, 0, 2, 2, -10, -7, 4, -8, 8, 3, 5, -1]);
+
+        apply_all_gravity(&mut moons);
+
+        assert_eq!(
+            moons.iter().map(|moon| moon.velocity).collect::<Vec<_>>(),
+            vec![
+                Vec3 { x: 3, y: -1, z: -1 },
+                Vec3 { x: 1, y: 3, z: 3 },
+                Vec3 { x: -3, y: 1, z: -3 },
+                Vec3 { x: -1, y: -3, z: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn simulate_10_steps_test() {
+        let input = vec![-1, 0, 2, 2, -10, -7, 4, -8, 8, 3, 5, -1];
+        let moon = |position: (i32, i32, i32), velocity: (i32, i32, i32)| Moon {
+            position: Vec3 {
+                x: position.0,
+                y: position.1,
+                z: position.2,
+            },
+            velocity: Vec3 {
+                x: velocity.0,
+                y: velocity.1,
+                z: velocity.2,
+            },
+        };
+
+        let moons = SimulationIter::new(parse_input(input.clone()))
+            .nth(9)
+            .unwrap();
+
+        assert_eq!(
+            moons,
+            vec![
+                moon((2, 1, -3), (-3, -2, 1)),
+                moon((1, -8, 0), (-1, 1, 3)),
+                moon((3, -6, 1), (3, 2, -3)),
+                moon((2, 0, 4), (1, -1, -1)),
+            ]
+        );
+        assert_eq!(solve_1(parse_input(input), 10), 179);
+    }
+
+    #[test]
+    fn larger_example_energy_test() {
+        let moons = parse_input(vec![-8, -10, 0, 5, 5, 10, 2, -7, 3, 9, -8, -3]);
+
+        assert_eq!(solve_1(moons, 100), 1940);
+    }
+
+    #[test]
+    fn zero_steps_energy_test() {
+        let moons = parse_input(vec![-1, 0, 2, 2, -10, -7, 4, -8, 8, 3, 5, -1]);
+        let moving = SimulationIter::new(moons.clone()).nth(9).unwrap();
+
+        assert_eq!(solve_1(moons, 0), 0);
+        assert_eq!(solve_1(moving, 0), 179);
+    }
+
+    #[test]
+    fn total_energy_test() {
+        let moons = parse_input(vec![-1, 0, 2, 2, -10, -7, 4, -8, 8, 3, 5, -1]);
+        let mut simulation = SimulationIter::new(moons.clone());
+
+        assert_eq!(total_energy(&moons), 0);
+        assert_eq!(total_energy(&simulation.nth(9).unwrap()), 179);
+    }
+
+    #[test]
+    fn three_moons_energy_test() {
+        let moons = parse_input(vec![-1, 0, 2, 2, -10, -7, 4, -8, 8]);
+
+        assert_eq!(solve_1(moons, 10), 190);
+    }
+
+    #[test]
+    fn concurrent_axis_periods_test() {
+        let moons = parse_input(vec![-8, -10, 0, 5, 5, 10, 2, -7, 3, 9, -8, -3]);
+        let sequential = (
+            axis_period(&moons, Axis::X),
+            axis_period(&moons, Axis::Y),
+            axis_period(&moons, Axis::Z),
+        );
+
+        assert_eq!(axis_periods(&moons), sequential);
+    }
+
+    #[test]
+    fn lcm_test() {
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(12, 18), 36);
+    }
+
+    #[test]
+    fn solve_2_test() {
+        let input = vec![-1, 0, 2, 2, -10, -7, 4, -8, 8, 3, 5, -1];
+
+        assert_eq!(solve_2(parse_input(input)), 2772);
+    }
+
+    #[test]
+    fn solve_2_larger_example_test() {
+        let input = vec![-8, -10, 0, 5, 5, 10, 2, -7, 3, 9, -8, -3];
+
+        assert_eq!(solve_2(parse_input(input)), 4_686_774_924);
+    }
+
+    #[test]
+    fn solve_2_three_moons_test() {
+        let moons = parse_input(vec![-1, 0, 2, 2, -10, -7, 4, -8, 8]);
+        let mut simulated = moons.clone();
+        let mut period = 0;
+
+        loop {
+            step_moons(&mut simulated);
+            period += 1;
+
+            if simulated == moons {
+                break;
+            }
+        }
+
+        assert_eq!(solve_2(moons), period);
+    }
 }
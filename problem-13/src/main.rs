@@ -1,331 +1,46 @@
-use std::{
-    collections::HashSet,
-    error::Error,
-    fs,
-    ops::{Add, Mul},
-};
+use intcode::{IntcodeError, IntcodeMachine};
+use std::{error::Error, fs};
 
-#[derive(Debug)]
-enum Mode {
-    Position,
-    Value,
-    Relative,
-}
-
-#[derive(Debug)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Direction {
-    fn next(&self, command: isize) -> Self {
-        if command == 0 {
-            match self {
-                Self::Up => Self::Left,
-                Self::Down => Self::Right,
-                Self::Left => Self::Down,
-                Self::Right => Self::Up,
-            }
-        } else {
-            match self {
-                Self::Up => Self::Right,
-                Self::Down => Self::Left,
-                Self::Left => Self::Up,
-                Self::Right => Self::Down,
-            }
-        }
-    }
-}
-
-impl Mode {
-    fn from_code(code: usize) -> Self {
-        match code {
-            0 => Self::Position,
-            1 => Self::Value,
-            2 => Self::Relative,
-            x => panic!("unknown code: {}", x),
-        }
-    }
-}
-
-#[derive(Debug)]
-enum Args<T> {
-    Zero,
-    One(T),
-    Two(T, T),
-    Three(T, T, T),
-}
+fn solve_1(program: &[isize]) -> Result<usize, IntcodeError> {
+    let test_input = 0;
 
-type CommandFn<T> = fn(&mut IntcodeMachine, Args<T>) -> Option<()>;
-// This probably isn't necessary to have as a separate struct
-struct Command<'a, T> {
-    machine: &'a mut IntcodeMachine,
-    command: CommandFn<T>,
-    args: Args<T>,
-}
+    let mut computer = IntcodeMachine::new(program, test_input, vec![]);
+    computer.run()?;
 
-impl<'a> Command<'a, usize> {
-    fn apply(self) -> Option<()> {
-        let cmd = self.command;
-        cmd(self.machine, self.args)
-    }
+    Ok(computer
+        .output()
+        .iter()
+        .skip(2)
+        .step_by(3)
+        .filter(|&&x| x == 2)
+        .count())
 }
 
-#[derive(Debug)]
-struct IntcodeMachine {
-    memory: Vec<isize>,
-    cmd_ptr: usize,
-    rel_base: isize,
-    input_signal: isize,
-    output: Vec<isize>,
-    position: (isize, isize),
-    direction: Direction,
-    should_paint: bool,
-    panels: Vec<Vec<isize>>,
-}
-
-impl IntcodeMachine {
-    fn new(program: &[isize], input_signal: isize, output: Vec<isize>) -> Self {
-        let mut panels = vec![vec![0; 1000]; 1000];
-        panels[500][500] = 1;
-
-        IntcodeMachine {
-            memory: program.to_vec(),
-            cmd_ptr: 0,
-            rel_base: 0,
-            input_signal,
-            output,
-            position: (0, 0),
-            direction: Direction::Up,
-            should_paint: true,
-            panels,
-        }
-    }
-
-    fn decode_instruction(&mut self) -> (usize, Vec<Mode>) {
-        let instruction = self.memory[self.cmd_ptr] as usize;
-        self.cmd_ptr += 1;
+// plays the game by keeping the paddle under the ball until every block is
+// gone, then returns the final score
+fn solve_2(program: &[isize]) -> Result<isize, IntcodeError> {
+    let joystick = 0;
 
-        let modes = (0..5)
-            .skip(2)
-            .map(|i| instruction / 10_usize.pow(i) % 10)
-            .map(Mode::from_code)
-            .collect();
+    let mut computer = IntcodeMachine::new(program, joystick, vec![]);
+    computer.write(0, 2);
 
-        (instruction % 100, modes)
-    }
-
-    fn get_current_memory_slice(&self, opcode: usize) -> &[isize] {
-        let arg_count = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            9 => 1,
-            99 => 0,
-            x => panic!("unknown opcode: {}", x),
+    let (mut ball, mut paddle, mut score) = (0, 0, 0);
+    while let Some(x) = computer.run_until_output()? {
+        let (y, tile) = match (computer.run_until_output()?, computer.run_until_output()?) {
+            (Some(y), Some(tile)) => (y, tile),
+            _ => break,
         };
 
-        &self.memory[self.cmd_ptr..(self.cmd_ptr + arg_count)]
-    }
-
-    fn build_args(&self, raw_args: &[isize], modes: Vec<Mode>) -> Args<usize> {
-        let cmd_ptr = self.cmd_ptr;
-        let args = raw_args
-            .iter()
-            .zip(modes)
-            .enumerate()
-            .map(|(i, (&raw_arg, mode))| match mode {
-                Mode::Position => raw_arg as usize,
-                Mode::Value => cmd_ptr + i as usize,
-                Mode::Relative => (self.rel_base + raw_arg) as usize,
-            })
-            .collect::<Vec<_>>();
-
-        match args.len() {
-            0 => Args::Zero,
-            1 => Args::One(args[0]),
-            2 => Args::Two(args[0], args[1]),
-            3 => Args::Three(args[0], args[1], args[2]),
-            _ => panic!("memory slice too big!"),
-        }
-    }
-
-    fn get_command(&mut self, opcode: usize, args: Args<usize>) -> Command<usize> {
-        let machine = self;
-        let command = match opcode {
-            1 => IntcodeMachine::add,
-            2 => IntcodeMachine::mul,
-            3 => IntcodeMachine::store,
-            4 => IntcodeMachine::push_output,
-            5 => IntcodeMachine::jump_if_true,
-            6 => IntcodeMachine::jump_if_false,
-            7 => IntcodeMachine::less_than,
-            8 => IntcodeMachine::equals,
-            9 => IntcodeMachine::mutate_rel_base,
-            99 => IntcodeMachine::halt,
-            x => panic!("unknown instruction: {}", x),
-        };
-
-        Command {
-            machine,
-            command,
-            args,
-        }
-    }
-
-    fn execute_step(&mut self) -> Option<()> {
-        let (opcode, modes) = self.decode_instruction();
-        let mem_slice = self.get_current_memory_slice(opcode);
-        let args = self.build_args(mem_slice, modes);
-
-        self.get_command(opcode, args).apply()
-    }
-
-    fn run(&mut self) {
-        self.for_each(|_| {});
-    }
-
-    fn add(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = self.memory[a_pos].add(self.memory[b_pos]);
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn mul(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = self.memory[a_pos].mul(self.memory[b_pos]);
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn store(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::One(storage_position) = args {
-            self.memory[storage_position] = self.input_signal;
-            self.cmd_ptr += 1;
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn push_output(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::One(data_position) = args {
-            self.output.push(self.memory[data_position]);
-            self.cmd_ptr += 1;
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn jump_if_true(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Two(should_jump_pos, instruction_position) = args {
-            self.cmd_ptr = if self.memory[should_jump_pos] != 0 {
-                self.memory[instruction_position] as usize
-            } else {
-                self.cmd_ptr + 2
-            };
-
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn jump_if_false(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Two(jump_on_zero_pos, instruction_position) = args {
-            self.cmd_ptr = if self.memory[jump_on_zero_pos] == 0 {
-                self.memory[instruction_position] as usize
-            } else {
-                self.cmd_ptr + 2
-            };
-
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn less_than(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = (self.memory[a_pos] < self.memory[b_pos]) as isize;
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn equals(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = (self.memory[a_pos] == self.memory[b_pos]) as isize;
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn mutate_rel_base(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::One(delta_pos) = args {
-            let delta = self.memory[delta_pos];
-            self.rel_base += delta;
-            self.cmd_ptr += 1;
-            Some(())
-        } else {
-            None
+        match (x, y, tile) {
+            (-1, 0, _) => score = tile,
+            (_, _, 3) => paddle = x,
+            (_, _, 4) => ball = x,
+            _ => {}
         }
+        *computer.input_mut() = (ball - paddle).signum();
     }
 
-    fn halt<T>(&mut self, _args: Args<T>) -> Option<()> {
-        None
-    }
-}
-
-impl Iterator for IntcodeMachine {
-    type Item = ();
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.execute_step()
-    }
-}
-
-fn solve_1(program: &[isize]) -> usize {
-    let memory = vec![0; 1000];
-    let input = [program.to_vec(), memory].concat();
-    let test_input = 0;
-
-    let mut computer = IntcodeMachine::new(&input, test_input, vec![]);
-    computer.run();
-
-    computer.output.iter().skip(2).step_by(3).filter(|&&x| x == 2).count()
-}
-
-fn solve_2(program: &[isize]) -> Vec<Vec<isize>> {
-    let mut memory = vec![0; 1000];
-    memory[0] = 2;
-    let input = [program.to_vec(), memory].concat();
-    let test_input = 0;
-
-    let mut computer = IntcodeMachine::new(&input, test_input, vec![]);
-    computer.run();
-
-    todo!();
+    Ok(score)
 }
 
 fn main() {
@@ -334,8 +49,17 @@ fn main() {
         std::process::exit(1);
     });
 
-    println!("first solution: {:?}", solve_1(&input));
-    // println!("second solution: {:?}", solve_2(&input));
+    let first = solve_1(&input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    println!("first solution: {:?}", first);
+
+    let second = solve_2(&input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    println!("second solution: {:?}", second);
 }
 
 fn get_input() -> Result<Vec<isize>, Box<dyn Error>> {
@@ -1,122 +1,49 @@
-use std::{
-    error::Error,
-    fs,
-    iter::StepBy,
-    ops::{Add, Mul, Range},
-};
-
-fn get_input() -> Result<Vec<usize>, Box<dyn Error>> {
+use intcode::{IntcodeError, IntcodeMachine};
+use std::{error::Error, fs};
+
+fn get_input() -> Result<Vec<isize>, Box<dyn Error>> {
     let result = fs::read_to_string("input.txt")?
         .trim()
         .split(',')
         .map(|line| line.parse())
-        .collect::<Result<Vec<usize>, std::num::ParseIntError>>()?;
+        .collect::<Result<Vec<isize>, std::num::ParseIntError>>()?;
 
     Ok(result)
 }
 
-#[derive(Debug)]
-struct IntcodeMachine {
-    memory: Vec<usize>,
-    cmd_ptr: StepBy<Range<usize>>,
-    print: bool,
-}
-
-impl IntcodeMachine {
-    fn new(memory: Vec<usize>) -> Self {
-        let end = memory.len();
-        IntcodeMachine {
-            memory,
-            cmd_ptr: (0..end).step_by(4),
-            print: false,
-        }
-    }
+fn run_program(input: &[isize], noun: isize, verb: isize) -> Result<isize, IntcodeError> {
+    let mut program = input.to_vec();
+    program[1] = noun;
+    program[2] = verb;
 
-    fn set_noun(mut self, x: usize) -> Self {
-        self.memory[1] = x;
-        self
-    }
+    let mut computer = IntcodeMachine::new(&program, 0, Vec::new());
+    computer.run()?;
+    Ok(computer.read(0))
+}
 
-    fn set_verb(mut self, x: usize) -> Self {
-        self.memory[2] = x;
-        self
-    }
+fn solve_1(input: &[isize]) -> Result<isize, IntcodeError> {
+    run_program(input, 12, 2)
+}
 
-    fn execute_step(&mut self) -> Option<()> {
-        self.cmd_ptr.next().and_then(|index| {
-            let cmd = self.get_command(index);
-            let args = self.get_args((index + 1, index + 2));
-            let result_address = self.memory[index + 3];
+fn find_inputs(program: &[isize], target: isize) -> Result<Option<(isize, isize)>, IntcodeError> {
+    let mut computer = IntcodeMachine::new(program, 0, Vec::new());
 
-            cmd(args).map(|result| {
-                self.memory[result_address] = result;
-                if self.print {
-                    println!("{:?}", self.memory);
-                }
-            })
-        })
-    }
+    for (noun, verb) in (0_isize..=99).flat_map(|i| (0_isize..=99).map(move |j| (i, j))) {
+        computer.reset();
+        computer.write(1, noun);
+        computer.write(2, verb);
+        computer.run()?;
 
-    fn run(&mut self) -> usize {
-        self.for_each(|()| {});
-        self.memory[0]
-    }
-
-    fn get_command(&self, index: usize) -> fn((usize, usize)) -> Option<usize> {
-        match self.memory[index] {
-            1 => Self::add,
-            2 => Self::mul,
-            99 => Self::halt,
-            x => panic!("unknown instruction: {}", x),
+        if computer.read(0) == target {
+            return Ok(Some((noun, verb)));
         }
     }
 
-    fn get_args(&self, (a, b): (usize, usize)) -> (usize, usize) {
-        let address_a = self.memory[a];
-        let address_b = self.memory[b];
-        (self.memory[address_a], self.memory[address_b])
-    }
-
-    fn add((a, b): (usize, usize)) -> Option<usize> {
-        Some(usize::add(a, b))
-    }
-
-    fn mul((a, b): (usize, usize)) -> Option<usize> {
-        Some(usize::mul(a, b))
-    }
-
-    fn halt<T>((_, _): (usize, usize)) -> Option<T> {
-        None
-    }
+    Ok(None)
 }
 
-impl Iterator for IntcodeMachine {
-    type Item = ();
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.execute_step()
-    }
-}
-
-fn solve_1(input: &[usize]) -> usize {
-    let mut computer = IntcodeMachine::new(input.to_vec()).set_noun(12).set_verb(2);
-    computer.run()
-}
-
-fn solve_2(input: &[usize]) -> usize {
-    let (noun, verb) = (0_usize..=99)
-        .flat_map(|i| (0_usize..=99).map(move |j| (i, j)))
-        .find(|(i, j)| {
-            let result = IntcodeMachine::new(input.to_vec())
-                .set_noun(*i)
-                .set_verb(*j)
-                .run();
-
-            result == 19_690_720
-        })
-        .unwrap();
-
-    (100 * noun) + verb
+fn solve_2(input: &[isize]) -> Result<Option<isize>, IntcodeError> {
+    Ok(find_inputs(input, 19_690_720)?.map(|(noun, verb)| (100 * noun) + verb))
 }
 
 fn main() {
@@ -125,6 +52,52 @@ fn main() {
         std::process::exit(1);
     });
 
-    println!("first solution: {}", solve_1(&input));
-    println!("second solution: {:?}", solve_2(&input));
+    let first = solve_1(&input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    println!("first solution: {}", first);
+
+    let second = solve_2(&input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    match second {
+        Some(second) => println!("second solution: {:?}", second),
+        None => {
+            eprintln!("no noun and verb produce 19690720");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn write_result_to_address_0() {
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        let result = Rc::new(Cell::new(None));
+        let recorded = Rc::clone(&result);
+
+        let mut computer =
+            IntcodeMachine::new(&program, 0, Vec::new()).on_write(move |addr, value| {
+                if addr == 0 {
+                    recorded.set(Some(value));
+                }
+            });
+        computer.run().unwrap();
+
+        assert_eq!(result.get(), Some(3500));
+    }
+
+    #[test]
+    fn find_inputs_test() {
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+
+        assert_eq!(find_inputs(&program, 100), Ok(Some((0, 0))));
+        assert_eq!(find_inputs(&program, -1), Ok(None));
+    }
 }
@@ -1,8 +1,13 @@
 use std::{
-    cmp::Ordering, collections::HashSet, error::Error, fs, num::ParseIntError, str::FromStr,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, fs,
+    num::ParseIntError,
+    str::FromStr,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 enum Step {
     Right(usize),
     Left(usize),
@@ -10,29 +15,100 @@ enum Step {
     Down(usize),
 }
 
+#[derive(Debug, PartialEq)]
+enum StepParseError {
+    UnknownDirection(String),
+    BadLength(ParseIntError),
+}
+
+impl fmt::Display for StepParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepParseError::UnknownDirection(step) => {
+                write!(f, "unknown direction in step {:?}", step)
+            }
+            StepParseError::BadLength(err) => write!(f, "bad step length: {}", err),
+        }
+    }
+}
+
+impl Error for StepParseError {}
+
+impl From<ParseIntError> for StepParseError {
+    fn from(err: ParseIntError) -> Self {
+        StepParseError::BadLength(err)
+    }
+}
+
 impl FromStr for Step {
-    type Err = ParseIntError;
+    type Err = StepParseError;
 
     fn from_str(str: &str) -> Result<Step, Self::Err> {
-        let (direction, steps) = str.split_at(1);
+        let mut chars = str.chars();
+        let direction = chars.next();
+        let steps = chars.as_str();
+
         match direction {
-            "R" => Ok(Step::Right(steps.parse()?)),
-            "L" => Ok(Step::Left(steps.parse()?)),
-            "U" => Ok(Step::Up(steps.parse()?)),
-            "D" => Ok(Step::Down(steps.parse()?)),
-            x => panic!("error parsing input: {}", x),
+            Some('R') => Ok(Step::Right(steps.parse()?)),
+            Some('L') => Ok(Step::Left(steps.parse()?)),
+            Some('U') => Ok(Step::Up(steps.parse()?)),
+            Some('D') => Ok(Step::Down(steps.parse()?)),
+            _ => Err(StepParseError::UnknownDirection(str.to_string())),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self {
+            Step::Right(_) => 'R',
+            Step::Left(_) => 'L',
+            Step::Up(_) => 'U',
+            Step::Down(_) => 'D',
+        };
+
+        write!(f, "{}{}", direction, self.distance())
+    }
+}
+
+impl Step {
+    fn distance(&self) -> usize {
+        match *self {
+            Step::Right(steps) | Step::Left(steps) | Step::Up(steps) | Step::Down(steps) => steps,
+        }
+    }
+
+    /// Every position the step passes through, ending at its destination
+    /// but not including `origin`.
+    fn iter_positions_from(&self, origin: &Position) -> impl Iterator<Item = Position> {
+        let (dx, dy) = match self {
+            Step::Right(_) => (1, 0),
+            Step::Left(_) => (-1, 0),
+            Step::Up(_) => (0, 1),
+            Step::Down(_) => (0, -1),
+        };
+        let Position(x, y) = *origin;
+
+        (1..=self.distance() as isize).map(move |i| Position(x + dx * i, y + dy * i))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 struct StepList(Vec<Step>);
 
+impl fmt::Display for StepList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps = self.0.iter().map(Step::to_string).collect::<Vec<_>>();
+
+        write!(f, "{}", steps.join(","))
+    }
+}
+
 impl StepList {
-    fn from_string(str: &str) -> Result<Self, ParseIntError> {
+    fn from_string(str: &str) -> Result<Self, StepParseError> {
         let step_list = str
             .split(',')
-            .map(|str| Step::from_str(str))
+            .map(Step::from_str)
             .collect::<Result<_, _>>()?;
 
         Ok(StepList(step_list))
@@ -41,9 +117,18 @@ impl StepList {
     fn into_iter(self) -> std::vec::IntoIter<Step> {
         self.0.into_iter()
     }
+
+    /// Total length of the wire, in unit steps.
+    fn total_length(&self) -> usize {
+        self.0.iter().map(Step::distance).sum()
+    }
+
+    fn step_count(&self) -> usize {
+        self.0.len()
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct Position(isize, isize);
 
 impl Position {
@@ -69,10 +154,16 @@ impl Position {
 
 impl PartialOrd for Position {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> Ordering {
         let a = self.distance_from_origin();
         let b = other.distance_from_origin();
 
-        a.partial_cmp(&b)
+        a.cmp(&b)
     }
 }
 
@@ -81,44 +172,19 @@ struct PositionList(Vec<Position>);
 
 impl PositionList {
     fn from_step_list(step_list: StepList) -> Self {
-        let step_positions = step_list
-            .into_iter()
-            .scan(Position::origin(), |last, step| {
-                let new_position = last.walk(step);
-
-                // could do this more efficiently with a 2D array of bools
-                let step_range: Vec<Position> = match new_position {
-                    Position(x, y) if x > last.0 => {
-                        (last.0..x).map(move |x| Position(x, y)).collect()
-                    }
-                    Position(x, y) if x < last.0 => (x + 1..=last.0)
-                        .rev()
-                        .map(move |x| Position(x, y))
-                        .collect(),
-                    Position(x, y) if y > last.1 => {
-                        (last.1..y).map(move |y| Position(x, y)).collect()
-                    }
-                    Position(x, y) if y < last.1 => (y + 1..=last.1)
-                        .rev()
-                        .map(move |y| Position(x, y))
-                        .collect(),
-                    _ => panic!("steps weren't parsed properly"),
-                };
-
-                *last = new_position;
-
-                Some(step_range)
-            })
-            .fold(Vec::new(), |mut full_path, mut path| {
-                full_path.append(&mut path);
-                full_path
-            });
+        let mut last = Position::origin();
 
-        Self(step_positions)
-    }
+        // one position per unit step, plus the origin
+        let mut step_positions = Vec::with_capacity(step_list.total_length() + 1);
+        step_positions.extend(std::iter::once(Position::origin()).chain(
+            step_list.into_iter().flat_map(move |step| {
+                let positions = step.iter_positions_from(&last);
+                last = last.walk(step);
+                positions
+            }),
+        ));
 
-    fn iter(&self) -> std::slice::Iter<Position> {
-        self.0.iter()
+        Self(step_positions)
     }
 
     fn into_iter(self) -> std::vec::IntoIter<Position> {
@@ -126,57 +192,406 @@ impl PositionList {
     }
 }
 
-fn get_input() -> Result<(StepList, StepList), Box<dyn Error>> {
-    let text = fs::read_to_string("input.txt")?;
+/// A straight run of wire, inclusive at both ends.
+#[derive(Clone, Debug)]
+struct Segment {
+    start: Position,
+    end: Position,
+}
 
-    let mut result = text.lines().map(|line| StepList::from_string(line.trim()));
+impl Segment {
+    fn contains(&self, p: &Position) -> bool {
+        let (min_x, max_x) = order(self.start.0, self.end.0);
+        let (min_y, max_y) = order(self.start.1, self.end.1);
+
+        (min_x..=max_x).contains(&p.0) && (min_y..=max_y).contains(&p.1)
+    }
+
+    /// Steps from the start of the segment to `p`, which should be on it.
+    fn steps_to(&self, p: &Position) -> usize {
+        debug_assert!(self.contains(p));
+        ((p.0 - self.start.0).abs() + (p.1 - self.start.1).abs()) as usize
+    }
 
-    Ok((result.next().unwrap()?, result.next().unwrap()?))
+    fn len(&self) -> usize {
+        self.steps_to(&self.end)
+    }
+
+    /// Every position both segments cover.
+    fn intersections(&self, other: &Segment) -> Vec<Position> {
+        let overlap = |a: (isize, isize), b: (isize, isize)| {
+            let (a_min, a_max) = order(a.0, a.1);
+            let (b_min, b_max) = order(b.0, b.1);
+            a_min.max(b_min)..=a_max.min(b_max)
+        };
+
+        let xs = overlap((self.start.0, self.end.0), (other.start.0, other.end.0));
+        let ys = overlap((self.start.1, self.end.1), (other.start.1, other.end.1));
+
+        xs.flat_map(|x| ys.clone().map(move |y| Position(x, y)))
+            .collect()
+    }
 }
 
-fn solve_1(wire_a: StepList, wire_b: StepList) -> isize {
-    PositionList::from_step_list(wire_a)
-        .into_iter()
-        .collect::<HashSet<Position>>()
-        .intersection(
-            &PositionList::from_step_list(wire_b)
+#[derive(Clone, Debug)]
+struct SegmentList(Vec<Segment>);
+
+impl SegmentList {
+    fn from_step_list(step_list: StepList) -> Self {
+        let mut segments = Vec::with_capacity(step_list.step_count());
+        segments.extend(
+            step_list
                 .into_iter()
-                .collect::<HashSet<Position>>(),
-        )
-        .filter(|position| **position != Position::origin())
-        .map(|position| position.distance_from_origin())
-        .min()
-        .unwrap()
+                .scan(Position::origin(), |last, step| {
+                    let end = last.walk(step);
+                    let start = std::mem::replace(last, end.clone());
+
+                    Some(Segment { start, end })
+                }),
+        );
+
+        Self(segments)
+    }
+
+    /// Every crossing with `other`, with the steps each wire took to get
+    /// there.
+    fn intersections<'a>(
+        &'a self,
+        other: &'a SegmentList,
+    ) -> impl Iterator<Item = (Position, usize, usize)> + 'a {
+        self.with_steps().flat_map(move |(a, a_steps)| {
+            other.with_steps().flat_map(move |(b, b_steps)| {
+                a.intersections(b).into_iter().map(move |position| {
+                    let a_steps = a_steps + a.steps_to(&position);
+                    let b_steps = b_steps + b.steps_to(&position);
+                    (position, a_steps, b_steps)
+                })
+            })
+        })
+    }
+
+    /// Each segment with the number of steps taken before reaching it.
+    fn with_steps(&self) -> impl Iterator<Item = (&Segment, usize)> {
+        self.0.iter().scan(0, |steps, segment| {
+            let before = *steps;
+            *steps += segment.len();
+            Some((segment, before))
+        })
+    }
 }
 
-fn solve_2(wire_a: StepList, wire_b: StepList) -> usize {
-    let wire_a_positions = PositionList::from_step_list(wire_a);
-    let wire_b_positions = PositionList::from_step_list(wire_b);
+fn order<T: PartialOrd>(a: T, b: T) -> (T, T) {
+    if a > b {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
 
-    let min = &wire_a_positions
+fn get_n_input(path: &str) -> Result<Vec<StepList>, Box<dyn Error>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(StepList::from_string)
+        .collect::<Result<_, _>>()?)
+}
+
+fn get_input() -> Result<Vec<StepList>, Box<dyn Error>> {
+    let wires = get_n_input("input.txt")?;
+
+    if wires.len() < 2 {
+        return Err("input.txt needs at least two wires".into());
+    }
+
+    Ok(wires)
+}
+
+/// Positions other than the origin that every wire passes through.
+fn n_wire_intersections(wires: &[StepList]) -> HashSet<Position> {
+    wires
+        .iter()
+        .map(|wire| {
+            PositionList::from_step_list(wire.clone())
+                .into_iter()
+                .filter(|position| *position != Position::origin())
+                .collect::<HashSet<_>>()
+        })
+        .fold(None, |common: Option<HashSet<Position>>, positions| {
+            Some(match common {
+                Some(common) => common.intersection(&positions).cloned().collect(),
+                None => positions,
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// The fewest combined steps for every wire to reach one shared position.
+fn n_wire_min_steps(wires: &[StepList]) -> Option<usize> {
+    // first visit only, since later ones can only take more steps
+    let first_steps: Vec<HashMap<Position, usize>> = wires
         .iter()
-        .collect::<HashSet<&Position>>()
-        .intersection(&wire_b_positions.iter().collect::<HashSet<&Position>>())
-        .filter(|&position| **position != Position::origin())
-        .map(|&position| {
-            let steps_a = wire_a_positions.iter().position(|x| x == position).unwrap();
-            let steps_b = wire_b_positions.iter().position(|x| x == position).unwrap();
-            steps_a + steps_b
+        .map(|wire| {
+            PositionList::from_step_list(wire.clone())
+                .into_iter()
+                .enumerate()
+                .fold(HashMap::new(), |mut steps, (count, position)| {
+                    steps.entry(position).or_insert(count);
+                    steps
+                })
         })
+        .collect();
+
+    n_wire_intersections(wires)
+        .iter()
+        .map(|position| first_steps.iter().map(|steps| steps[position]).sum())
         .min()
-        .unwrap();
-    *min
+}
+
+fn solve_1(wire_a: StepList, wire_b: StepList) -> isize {
+    SegmentList::from_step_list(wire_a)
+        .intersections(&SegmentList::from_step_list(wire_b))
+        .filter(|(position, _, _)| *position != Position::origin())
+        .map(|(position, _, _)| position.distance_from_origin())
+        .min()
+        .unwrap()
+}
+
+fn solve_2(wire_a: StepList, wire_b: StepList) -> usize {
+    SegmentList::from_step_list(wire_a)
+        .intersections(&SegmentList::from_step_list(wire_b))
+        .filter(|(position, _, _)| *position != Position::origin())
+        .map(|(_, steps_a, steps_b)| steps_a + steps_b)
+        .min()
+        .unwrap()
 }
 
 fn main() {
-    let input = get_input().unwrap_or_else(|err| {
+    let wires = get_input().unwrap_or_else(|err| {
         eprintln!("{}", err);
         std::process::exit(1);
     });
 
-    println!(
-        "first solution: {:?}",
-        solve_1(input.0.clone(), input.1.clone())
-    );
-    println!("second solution: {:?}", solve_2(input.0, input.1));
+    match wires.as_slice() {
+        [wire_a, wire_b] => {
+            println!(
+                "first solution: {:?}",
+                solve_1(wire_a.clone(), wire_b.clone())
+            );
+            println!(
+                "second solution: {:?}",
+                solve_2(wire_a.clone(), wire_b.clone())
+            );
+        }
+        // the segments only pair up two wires, so more go through every position
+        wires => {
+            let closest = n_wire_intersections(wires)
+                .iter()
+                .map(Position::distance_from_origin)
+                .min()
+                .expect("the wires never all cross");
+            let steps = n_wire_min_steps(wires).expect("the wires never all cross");
+
+            println!("first solution: {:?}", closest);
+            println!("second solution: {:?}", steps);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wires(a: &str, b: &str) -> (StepList, StepList) {
+        (
+            StepList::from_string(a).unwrap(),
+            StepList::from_string(b).unwrap(),
+        )
+    }
+
+    #[test]
+    fn segment_contains_and_steps() {
+        let segment = Segment {
+            start: Position(3, 2),
+            end: Position(-2, 2),
+        };
+
+        assert!(segment.contains(&Position(0, 2)));
+        assert!(segment.contains(&Position(-2, 2)));
+        assert!(!segment.contains(&Position(0, 3)));
+        assert_eq!(segment.steps_to(&Position(0, 2)), 3);
+        assert_eq!(segment.len(), 5);
+    }
+
+    #[test]
+    fn crossing_and_overlapping_segments() {
+        let horizontal = Segment {
+            start: Position(0, 0),
+            end: Position(4, 0),
+        };
+        let vertical = Segment {
+            start: Position(2, 3),
+            end: Position(2, -1),
+        };
+        let overlapping = Segment {
+            start: Position(6, 0),
+            end: Position(3, 0),
+        };
+
+        assert_eq!(horizontal.intersections(&vertical), vec![Position(2, 0)]);
+        assert_eq!(
+            horizontal.intersections(&overlapping),
+            vec![Position(3, 0), Position(4, 0)]
+        );
+        assert!(vertical.intersections(&overlapping).is_empty());
+    }
+
+    #[test]
+    fn examples() {
+        let examples = [
+            ("R8,U5,L5,D3", "U7,R6,D4,L4", 6, 30),
+            (
+                "R75,D30,R83,U83,L12,D49,R71,U7,L72",
+                "U62,R66,U55,R34,D71,R55,D58,R83",
+                159,
+                610,
+            ),
+            (
+                "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
+                "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+                135,
+                410,
+            ),
+        ];
+
+        examples.iter().for_each(|&(a, b, distance, steps)| {
+            let (wire_a, wire_b) = wires(a, b);
+            assert_eq!(solve_1(wire_a.clone(), wire_b.clone()), distance);
+            assert_eq!(solve_2(wire_a, wire_b), steps);
+        });
+    }
+
+    #[test]
+    fn position_list_includes_each_endpoint() {
+        let positions = |wire| PositionList::from_step_list(StepList::from_string(wire).unwrap()).0;
+
+        assert_eq!(positions("R1"), vec![Position::origin(), Position(1, 0)]);
+        assert_eq!(positions("R3").last(), Some(&Position(3, 0)));
+        assert_eq!(positions("R2,D1").last(), Some(&Position(2, -1)));
+    }
+
+    #[test]
+    fn position_lists_agree_with_examples() {
+        let wires = [
+            StepList::from_string("R8,U5,L5,D3").unwrap(),
+            StepList::from_string("U7,R6,D4,L4").unwrap(),
+        ];
+
+        assert_eq!(
+            n_wire_intersections(&wires)
+                .iter()
+                .map(Position::distance_from_origin)
+                .min(),
+            Some(6)
+        );
+        assert_eq!(n_wire_min_steps(&wires), Some(30));
+    }
+
+    #[test]
+    fn three_wires() {
+        let wires = ["R5,U5", "U5,R5", "R2,U2,R3,U3"]
+            .iter()
+            .map(|wire| StepList::from_string(wire).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            n_wire_intersections(&wires),
+            vec![Position(5, 5)].into_iter().collect()
+        );
+        assert_eq!(n_wire_min_steps(&wires), Some(30));
+        assert_eq!(n_wire_min_steps(&wires[..2]), Some(20));
+        assert!(n_wire_intersections(&[]).is_empty());
+    }
+
+    #[test]
+    fn step_list_roundtrip() {
+        let step_list = StepList::from_string("R8,U10,L5,D3").unwrap();
+
+        assert_eq!(step_list.to_string(), "R8,U10,L5,D3");
+        assert_eq!(
+            StepList::from_string(&format!("{}", step_list)),
+            Ok(step_list)
+        );
+    }
+
+    #[test]
+    fn step_parse_errors() {
+        assert_eq!(
+            "X5".parse::<Step>(),
+            Err(StepParseError::UnknownDirection("X5".to_string()))
+        );
+        assert_eq!(
+            "".parse::<Step>(),
+            Err(StepParseError::UnknownDirection("".to_string()))
+        );
+        assert!(matches!(
+            StepList::from_string("R5,Ux"),
+            Err(StepParseError::BadLength(_))
+        ));
+    }
+
+    #[test]
+    fn step_list_lengths() {
+        let steps = StepList::from_string("R8,U5,L5,D3").unwrap();
+
+        assert_eq!(steps.total_length(), 21);
+        assert_eq!(steps.step_count(), 4);
+    }
+
+    #[test]
+    fn positions_along_each_step() {
+        let positions = |step: Step, x, y| {
+            step.iter_positions_from(&Position(x, y))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            positions(Step::Right(3), 0, 0),
+            vec![Position(1, 0), Position(2, 0), Position(3, 0)]
+        );
+        assert_eq!(
+            positions(Step::Left(3), 1, 5),
+            vec![Position(0, 5), Position(-1, 5), Position(-2, 5)]
+        );
+        assert_eq!(
+            positions(Step::Up(2), 4, -1),
+            vec![Position(4, 0), Position(4, 1)]
+        );
+        assert_eq!(
+            positions(Step::Down(2), -3, 1),
+            vec![Position(-3, 0), Position(-3, -1)]
+        );
+        assert!(positions(Step::Right(0), 2, 2).is_empty());
+        assert!(positions(Step::Down(0), 0, 0).is_empty());
+    }
+
+    #[test]
+    fn segments_match_unit_positions() {
+        let (wire_a, wire_b) = wires("R8,U5,L5,D3", "U7,R6,D4,L4");
+        let positions = PositionList::from_step_list(wire_a.clone());
+        let segments = SegmentList::from_step_list(wire_a);
+
+        positions
+            .0
+            .iter()
+            .enumerate()
+            .for_each(|(steps, position)| {
+                let (segment, before) = segments
+                    .with_steps()
+                    .find(|(segment, _)| segment.contains(position))
+                    .unwrap();
+                assert_eq!(before + segment.steps_to(position), steps);
+            });
+        assert_eq!(SegmentList::from_step_list(wire_b).0.len(), 4);
+    }
 }
@@ -1,25 +1,29 @@
-use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug)]
-struct ElfPassword {
-    min: usize,
+struct ElfPassword<const N: usize> {
     max: usize,
-    value: [usize; 6], // this could be its own type
+    value: [usize; N], // this could be its own type
 }
 
-impl ElfPassword {
+impl<const N: usize> ElfPassword<N> {
     fn new(min: usize, max: usize) -> Self {
-        let mut value = [0; 6];
-        (0_usize..6)
-            .map(|i| (5 - i, min / 10_usize.pow(i as u32) % 10_usize))
-            .for_each(|(i, x)| value[i] = x);
+        let value = Self::digits(min);
 
-        let mut result = Self { min, value, max };
+        let mut result = Self { max, value };
         result.apply_inscrease_rule();
         result
     }
 
-    // this will fail for range 000000-999999
+    fn digits(number: usize) -> [usize; N] {
+        let mut value = [0; N];
+        (0_usize..N)
+            .map(|i| (N - 1 - i, number / 10_usize.pow(i as u32) % 10_usize))
+            .for_each(|(i, x)| value[i] = x);
+        value
+    }
+
+    // this will fail for a range of all N digits, e.g. 000000-999999
     fn incr_value(&mut self) -> &mut Self {
         self.value.iter_mut().rev().fold(true, |carry, val| {
             let next_val = if carry { *val + 1 } else { *val };
@@ -73,11 +77,43 @@ impl ElfPassword {
             .map(|(i, val)| val * 10_usize.pow(i as u32))
             .sum()
     }
+
+    /// Collapses runs of the same digit into `(digit, run_length)` pairs.
+    fn digit_run_lengths(value: &[usize; N]) -> Vec<(usize, usize)> {
+        value.iter().fold(Vec::new(), |mut runs, &digit| {
+            match runs.last_mut() {
+                Some((last, len)) if *last == digit => *len += 1,
+                _ => runs.push((digit, 1)),
+            }
+
+            runs
+        })
+    }
+
+    fn has_double_repeat(value: &[usize; N]) -> bool {
+        Self::digit_run_lengths(value)
+            .iter()
+            .any(|(_, len)| *len >= 2)
+    }
+
+    fn has_strict_double_repeat(value: &[usize; N]) -> bool {
+        Self::digit_run_lengths(value)
+            .iter()
+            .any(|(_, len)| *len == 2)
+    }
+}
+
+impl<const N: usize> fmt::Display for ElfPassword<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value
+            .iter()
+            .try_for_each(|digit| write!(f, "{}", digit))
+    }
 }
 
 // should probably create an Iter type for this
-impl Iterator for ElfPassword {
-    type Item = [usize; 6];
+impl<const N: usize> Iterator for ElfPassword<N> {
+    type Item = [usize; N];
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.is_in_range() {
@@ -90,35 +126,26 @@ impl Iterator for ElfPassword {
     }
 }
 
-fn has_double_repeat(value: &[usize; 6]) -> bool {
-    value.windows(2).any(|window| window[0] == window[1])
-}
+trait PasswordRule<const N: usize>: Fn(&[usize; N]) -> bool + Send + Sync {}
 
-fn has_strict_double_repeat(value: &[usize; 6]) -> bool {
-    value
-        .windows(2)
-        .filter(|window| window[0] == window[1])
-        .fold(HashMap::new(), |mut map, window| {
-            let digit = window[0];
-            let counter = map.entry(digit).or_insert(0);
-            *counter += 1;
+impl<F, const N: usize> PasswordRule<N> for F where F: Fn(&[usize; N]) -> bool + Send + Sync {}
 
-            map
-        })
-        .iter()
-        .any(|(_, count)| *count == 1)
+/// Counts the passwords in range that pass every rule.
+fn count_passwords_with_rules<const N: usize>(
+    (a, b): (usize, usize),
+    rules: &[&dyn PasswordRule<N>],
+) -> usize {
+    ElfPassword::<N>::new(a, b)
+        .filter(|value| rules.iter().all(|rule| rule(value)))
+        .count()
 }
 
-fn solve_1((a, b): (usize, usize)) -> usize {
-    ElfPassword::new(a, b)
-        .filter(has_double_repeat)
-        .count()
+fn solve_1(input: (usize, usize)) -> usize {
+    count_passwords_with_rules::<6>(input, &[&ElfPassword::has_double_repeat])
 }
 
-fn solve_2((a, b): (usize, usize)) -> usize {
-    ElfPassword::new(a, b)
-        .filter(has_strict_double_repeat)
-        .count()
+fn solve_2(input: (usize, usize)) -> usize {
+    count_passwords_with_rules::<6>(input, &[&ElfPassword::has_strict_double_repeat])
 }
 
 fn get_input() -> (usize, usize) {
@@ -134,3 +161,93 @@ fn main() {
     println!("first solution: {:?}", solve_1(input));
     println!("second solution: {:?}", solve_2(input));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn four_digit_passwords() {
+        let passwords = ElfPassword::<4>::new(1000, 9999).collect::<Vec<_>>();
+
+        assert_eq!(passwords[0], [1, 1, 1, 1]);
+        assert!(passwords.contains(&[2, 2, 2, 2]));
+        assert!(passwords.contains(&[1, 2, 3, 4]));
+        // every non-decreasing run of 1-9 digits except 9999 itself
+        assert_eq!(passwords.len(), 494);
+        assert_eq!(
+            passwords
+                .iter()
+                .filter(|value| ElfPassword::has_double_repeat(value))
+                .count(),
+            368
+        );
+    }
+
+    #[test]
+    fn two_digit_passwords() {
+        let passwords = ElfPassword::<2>::new(10, 99).collect::<Vec<_>>();
+
+        assert_eq!(passwords.len(), 44);
+        assert_eq!(
+            passwords
+                .iter()
+                .filter(|value| ElfPassword::has_strict_double_repeat(value))
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn six_digit_rules() {
+        assert!(ElfPassword::has_double_repeat(&[1, 1, 1, 1, 1, 1]));
+        assert!(!ElfPassword::has_strict_double_repeat(&[1, 2, 3, 4, 4, 4]));
+        assert!(ElfPassword::has_strict_double_repeat(&[1, 1, 1, 1, 2, 2]));
+    }
+
+    #[test]
+    fn run_lengths() {
+        assert_eq!(
+            ElfPassword::digit_run_lengths(&[1, 1, 2, 2, 3, 3]),
+            vec![(1, 2), (2, 2), (3, 2)]
+        );
+        assert_eq!(
+            ElfPassword::digit_run_lengths(&[1, 1, 1, 1, 2, 2]),
+            vec![(1, 4), (2, 2)]
+        );
+        assert_eq!(
+            ElfPassword::digit_run_lengths(&[1, 2, 3]),
+            vec![(1, 1), (2, 1), (3, 1)]
+        );
+    }
+
+    #[test]
+    fn combined_rules() {
+        let input = get_input();
+        let no_sevens = |value: &[usize; 6]| !value.contains(&7);
+        let count = count_passwords_with_rules(
+            input,
+            &[&ElfPassword::has_strict_double_repeat, &no_sevens],
+        );
+
+        assert!(count < solve_2(input));
+        assert_eq!(
+            count,
+            ElfPassword::<6>::new(input.0, input.1)
+                .filter(|value| ElfPassword::has_strict_double_repeat(value) && no_sevens(value))
+                .count()
+        );
+        assert_eq!(
+            count_passwords_with_rules::<6>(input, &[]),
+            ElfPassword::<6>::new(input.0, input.1).count()
+        );
+    }
+
+    #[test]
+    fn display_digits() {
+        let (_, max) = get_input();
+
+        assert_eq!(ElfPassword::<6>::new(123455, max).to_string(), "123455");
+        assert_eq!(ElfPassword::<6>::new(0, 10).to_string(), "000000");
+    }
+}
@@ -1,100 +1,227 @@
 use std::{
-    collections::{hash_set, HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     error::Error,
-    fs,
+    fmt, fs,
     iter::Iterator,
 };
 
-
 type Relationship = (String, String);
 
-// This solution is definitely a brute-force solution!
-// I rely heavily on HashSets to get a unique list of nodes
-// as well as comparing pathways. I'm sure there's some graph algorith to properly find
-// the shortest path for part-2.
+// Part-1 is definitely a brute-force solution!
+// I rely heavily on HashSets to get a unique list of nodes.
+// Part-2 searches outwards from what YOU orbits until it reaches what SAN orbits.
 // I do however, love how easy it was to travers the HashMap with an iterator!
 
-#[derive(Debug)]
-struct RelationshipIter<'a> {
-    map: HashMap<&'a str, &'a str>,
-    nodes: hash_set::IntoIter<&'a str>,
-    next: Option<&'a str>,
+#[derive(Debug, PartialEq)]
+enum OrbitError {
+    Cycle(String),
+}
+
+impl fmt::Display for OrbitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrbitError::Cycle(node) => write!(f, "{} is part of an orbit cycle", node),
+        }
+    }
 }
 
-impl<'a> RelationshipIter<'a> {
-    fn from_list(list: &'a [Relationship]) -> Self {
-        let map: HashMap<&str, &str> = list
+/// Counts every orbit, reporting a cycle in the map instead of looping on it.
+fn try_count(edges: &[Relationship]) -> Result<usize, OrbitError> {
+    RelationshipGraph::from_relationships(edges).orbit_count()
+}
+
+fn solve_1(input: &[Relationship]) -> Result<usize, OrbitError> {
+    try_count(input)
+}
+
+/// Splits the total orbit count into `(direct, indirect)`. Every object
+/// directly orbits exactly one parent, so there's one direct orbit per edge.
+fn orbit_breakdown(edges: &[Relationship]) -> Result<(usize, usize), OrbitError> {
+    let graph = RelationshipGraph::from_relationships(edges);
+    let total = graph.orbit_count()?;
+    let direct = graph.parent_of.len();
+
+    Ok((direct, total - direct))
+}
+
+/// The chain of objects from `from` up to the first ancestor it shares with
+/// `to`, then back down to `to`. `None` if they share no ancestor, or if
+/// either of them is caught in a cycle.
+fn path_between(edges: &[Relationship], from: &str, to: &str) -> Option<Vec<String>> {
+    let graph = RelationshipGraph::from_relationships(edges);
+    let ancestors = |node| {
+        std::iter::once(Ok(node))
+            .chain(graph.path_to_root(node))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+    };
+
+    let from_chain = ancestors(from)?;
+    let to_chain = ancestors(to)?;
+
+    let (to_common, from_common) = to_chain.iter().enumerate().find_map(|(i, node)| {
+        from_chain
             .iter()
-            .map(|(parent, child)| (child.as_str(), parent.as_str()))
-            .collect();
-        let mut nodes = map.keys().copied().collect::<HashSet<_>>().into_iter();
+            .position(|other| other == node)
+            .map(|j| (i, j))
+    })?;
 
-        let next = nodes.next();
+    Some(
+        from_chain[..=from_common]
+            .iter()
+            .chain(to_chain[..to_common].iter().rev())
+            .map(|node| node.to_string())
+            .collect(),
+    )
+}
 
-        RelationshipIter { map, next, nodes }
+#[derive(Debug, PartialEq)]
+enum TransferError {
+    MissingNode(String),
+    NotConnected(String, String),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransferError::MissingNode(node) => write!(f, "{} isn't in the orbit map", node),
+            TransferError::NotConnected(a, b) => write!(f, "{} and {} aren't connected", a, b),
+        }
+    }
+}
+
+/// The orbit map built once, so it can answer several questions.
+#[derive(Debug)]
+struct RelationshipGraph {
+    parent_of: HashMap<String, String>,
+    neighbors_of: HashMap<String, Vec<String>>, // every edge, in both directions
+    root: String,
+}
+
+impl RelationshipGraph {
+    fn from_relationships(list: &[Relationship]) -> Self {
+        Self::from_relationships_with_root(list, "COM")
     }
 
-    fn with_single_traversal_from_node(list: &'a [Relationship], node: &'a str) -> Self {
-        let map: HashMap<_, _> = list
+    /// Counts orbits up to `root` rather than the usual "COM".
+    fn from_relationships_with_root(list: &[Relationship], root: &str) -> Self {
+        let parent_of = list
             .iter()
-            .map(|(parent, child)| (child.as_str(), parent.as_str()))
+            .map(|(parent, child)| (child.clone(), parent.clone()))
             .collect();
-        let nodes = HashSet::new().into_iter();
-        let next = Some(node);
 
-        RelationshipIter { map, next, nodes }
+        let mut neighbors_of: HashMap<String, Vec<String>> = HashMap::new();
+        list.iter().for_each(|(parent, child)| {
+            neighbors_of
+                .entry(parent.clone())
+                .or_default()
+                .push(child.clone());
+            neighbors_of
+                .entry(child.clone())
+                .or_default()
+                .push(parent.clone());
+        });
+
+        RelationshipGraph {
+            parent_of,
+            neighbors_of,
+            root: root.to_string(),
+        }
     }
-}
 
-impl<'a> Iterator for RelationshipIter<'a> {
-    type Item = &'a str;
+    /// Everything `node` orbits, directly or not, from its parent up to the root.
+    /// Ends with an error if the parents lead back to an object already on the
+    /// path, `node` included.
+    fn path_to_root<'a>(
+        &'a self,
+        node: &str,
+    ) -> impl Iterator<Item = Result<&'a str, OrbitError>> + 'a {
+        // the root might orbit something itself, but that's outside the map we count
+        let parent = move |node: &str| {
+            Some(node)
+                .filter(|&node| node != self.root)
+                .and_then(|node| self.parent_of.get(node))
+                .map(String::as_str)
+        };
+        let start = node.to_string();
+        let mut seen = HashSet::new();
+        let mut next = parent(node);
+
+        std::iter::from_fn(move || {
+            let node = next.take()?;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let next = self
-            .next
-            .filter(|node| node != &"COM")
-            .or_else(|| self.nodes.next()) // I really like these helper methods
-            .take();
+            if node == start || !seen.insert(node) {
+                return Some(Err(OrbitError::Cycle(node.to_string())));
+            }
 
-        self.next = next.and_then(|node| self.map.get(node)).copied();
+            next = parent(node);
+            Some(Ok(node))
+        })
+    }
 
-        next
+    /// How many hops `node` is from the root.
+    fn orbit_depth(&self, node: &str) -> Result<usize, OrbitError> {
+        self.path_to_root(node)
+            .try_fold(0, |depth, node| node.map(|_| depth + 1))
     }
-}
 
-fn solve_1(input: &[Relationship]) -> usize {
-    RelationshipIter::from_list(input).count()
-}
+    fn orbit_count(&self) -> Result<usize, OrbitError> {
+        self.parent_of
+            .keys()
+            .map(|node| self.orbit_depth(node))
+            .sum()
+    }
 
-fn resolve_none_on_found<'a>(
-    set: HashSet<&'a str>,
-) -> impl FnMut(&mut bool, &'a str) -> Option<&'a str> {
-    move |found, node| {
-        if *found {
-            None
-        } else {
-            *found = set.contains(node);
-            Some(node)
+    /// The orbital transfers needed to move from the object `a` orbits to the
+    /// object `b` orbits.
+    fn transfers_between(&self, a: &str, b: &str) -> Result<usize, TransferError> {
+        // only something that orbits can transfer
+        let parent = |node: &str| {
+            self.parent_of
+                .get(node)
+                .ok_or_else(|| TransferError::MissingNode(node.to_string()))
+        };
+        let (from, to) = (parent(a)?, parent(b)?);
+
+        self.shortest_path(from, to)
+            .map(|path| path.len() - 1)
+            .ok_or_else(|| TransferError::NotConnected(a.to_string(), b.to_string()))
+    }
+
+    /// The shortest chain of objects from `from` to `to`, both included.
+    /// This doesn't assume every object orbits exactly one other.
+    fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut came_from = HashMap::new();
+        let mut queue = VecDeque::new();
+        came_from.insert(from, from);
+        queue.push_back(from);
+
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = std::iter::successors(Some(node), |node| {
+                    Some(came_from[node]).filter(|&previous| previous != *node)
+                })
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for next in self.neighbors_of.get(node).into_iter().flatten() {
+                if !came_from.contains_key(next.as_str()) {
+                    came_from.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
         }
+
+        None
     }
 }
 
-fn solve_2(input: &[Relationship]) -> usize {
-    let (me, san) = ("YOU", "SAN");
-    let my_visited_nodes: HashSet<_> =
-        RelationshipIter::with_single_traversal_from_node(input, &me).collect();
-    let (san_count, common_node) = RelationshipIter::with_single_traversal_from_node(input, &san)
-        .scan(false, resolve_none_on_found(my_visited_nodes))
-        .enumerate()
-        .last()
-        .unwrap();
-    let (my_count, _) = RelationshipIter::with_single_traversal_from_node(input, &me)
-        .take_while(|node| node != &common_node)
-        .enumerate()
-        .last()
-        .unwrap();
-
-    san_count + my_count - 1
+fn solve_2(input: &[Relationship]) -> Result<usize, TransferError> {
+    RelationshipGraph::from_relationships(input).transfers_between("YOU", "SAN")
 }
 
 fn main() {
@@ -103,8 +230,28 @@ fn main() {
         std::process::exit(1);
     });
 
-    println!("first solution: {:?}", solve_1(input.as_ref()));
-    println!("second solution: {:?}", solve_2(input.as_ref()));
+    let orbits = solve_1(input.as_ref()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    println!("first solution: {:?}", orbits);
+    if let Ok((direct, indirect)) = orbit_breakdown(input.as_ref()) {
+        println!("  {} direct and {} indirect", direct, indirect);
+    }
+
+    match solve_2(input.as_ref()) {
+        Ok(transfers) => {
+            println!("second solution: {:?}", transfers);
+            if let Some(path) = path_between(input.as_ref(), "YOU", "SAN") {
+                println!("  via {}", path.join(" -> "));
+            }
+        }
+        Err(err) => {
+            eprintln!("no second solution: {}", err);
+            std::process::exit(1);
+        }
+    }
 }
 
 fn get_input() -> Result<Vec<Relationship>, Box<dyn Error>> {
@@ -114,3 +261,150 @@ fn get_input() -> Result<Vec<Relationship>, Box<dyn Error>> {
         .map(|sides| (sides[0].to_string(), sides[1].to_string()))
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Relationship> {
+        input
+            .split_whitespace()
+            .map(|line| line.split(')').collect::<Vec<_>>())
+            .map(|sides| (sides[0].to_string(), sides[1].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn count_orbits() {
+        let input = parse("COM)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L");
+
+        assert_eq!(solve_1(&input), Ok(42));
+        assert_eq!(orbit_breakdown(&input), Ok((11, 31)));
+    }
+
+    #[test]
+    fn count_orbits_around_sun() {
+        let input = parse("SUN)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L");
+        assert_eq!(
+            RelationshipGraph::from_relationships_with_root(&input, "SUN").orbit_count(),
+            Ok(42)
+        );
+
+        // orbits past the root aren't counted
+        let input = parse("COM)SUN SUN)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L");
+        assert_eq!(solve_1(&input), Ok(54));
+        assert_eq!(
+            RelationshipGraph::from_relationships_with_root(&input, "SUN").orbit_count(),
+            Ok(42)
+        );
+    }
+
+    #[test]
+    fn count_orbits_with_cycle() {
+        assert_eq!(
+            try_count(&parse("COM)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L")),
+            Ok(42)
+        );
+        assert!(matches!(
+            try_count(&parse("A)B B)A")),
+            Err(OrbitError::Cycle(_))
+        ));
+
+        let graph = RelationshipGraph::from_relationships(&parse("COM)A A)B B)C C)A"));
+        assert_eq!(
+            graph.path_to_root("C").collect::<Vec<_>>(),
+            vec![Ok("B"), Ok("A"), Err(OrbitError::Cycle("C".to_string()))]
+        );
+        assert!(matches!(graph.orbit_count(), Err(OrbitError::Cycle(_))));
+        assert!(matches!(
+            solve_1(&parse("COM)B A)B B)A")),
+            Err(OrbitError::Cycle(_))
+        ));
+    }
+
+    #[test]
+    fn transfers_to_santa() {
+        let input = parse("COM)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L K)YOU I)SAN");
+
+        assert_eq!(solve_2(&input), Ok(4));
+    }
+
+    #[test]
+    fn graph_queries() {
+        let input = parse("COM)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L K)YOU I)SAN");
+        let graph = RelationshipGraph::from_relationships(&input);
+
+        assert_eq!(
+            graph.path_to_root("YOU").collect::<Result<Vec<_>, _>>(),
+            Ok(vec!["K", "J", "E", "D", "C", "B", "COM"])
+        );
+        assert_eq!(graph.path_to_root("COM").count(), 0);
+        assert_eq!(graph.orbit_count(), Ok(54));
+        assert_eq!(graph.transfers_between("YOU", "SAN"), Ok(4));
+        assert_eq!(graph.transfers_between("L", "H"), Ok(6));
+        assert_eq!(graph.orbit_depth("YOU"), Ok(7));
+        assert_eq!(graph.orbit_depth("COM"), Ok(0));
+        assert_eq!(
+            graph.shortest_path("YOU", "SAN").map(|path| path.len()),
+            Some(7)
+        );
+        assert_eq!(graph.shortest_path("YOU", "nowhere"), None);
+    }
+
+    #[test]
+    fn shortest_path_through_diamond() {
+        // D orbits both C and E, so walking up to the root would take the long way
+        let input = parse("COM)A A)B B)C E)D C)D D)YOU COM)E E)SAN");
+        let graph = RelationshipGraph::from_relationships(&input);
+
+        assert_eq!(graph.transfers_between("YOU", "SAN"), Ok(1));
+        assert_eq!(
+            graph.shortest_path("YOU", "SAN"),
+            Some(
+                ["YOU", "D", "E", "SAN"]
+                    .iter()
+                    .map(|node| node.to_string())
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn transfers_without_santa() {
+        let input = parse("COM)B B)C C)D K)YOU");
+
+        assert_eq!(
+            solve_2(&input),
+            Err(TransferError::MissingNode("SAN".to_string()))
+        );
+        assert_eq!(
+            solve_2(&parse("COM)YOU X)SAN")),
+            Err(TransferError::NotConnected(
+                "YOU".to_string(),
+                "SAN".to_string()
+            ))
+        );
+        assert_eq!(
+            TransferError::NotConnected("L".to_string(), "H".to_string()).to_string(),
+            "L and H aren't connected"
+        );
+    }
+
+    #[test]
+    fn path_to_santa() {
+        let input = parse("COM)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L K)YOU I)SAN");
+
+        assert_eq!(
+            path_between(&input, "YOU", "SAN"),
+            Some(
+                ["YOU", "K", "J", "E", "D", "I", "SAN"]
+                    .iter()
+                    .map(|node| node.to_string())
+                    .collect()
+            )
+        );
+        assert_eq!(path_between(&input, "H", "H"), Some(vec!["H".to_string()]));
+        assert_eq!(path_between(&input, "YOU", "nowhere"), None);
+        assert_eq!(path_between(&parse("COM)A A)B B)A"), "A", "COM"), None);
+    }
+}
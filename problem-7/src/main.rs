@@ -1,342 +1,111 @@
-use std::{
-    collections::VecDeque,
-    error::Error,
-    fs,
-    ops::{Add, Mul},
-    sync::mpsc::{channel, Receiver, Sender},
-    thread::spawn,
-};
+use intcode::{IntcodeError, IntcodeMachine};
+use std::{collections::VecDeque, error::Error, fs};
 
-#[derive(Debug)]
-enum Mode {
-    Position,
-    Value,
-}
-
-impl Mode {
-    fn from_code(code: usize) -> Self {
-        match code {
-            0 => Self::Position,
-            1 => Self::Value,
-            x => panic!("unknown code: {}", x),
-        }
-    }
-}
-
-#[derive(Debug)]
-enum Args<T> {
-    Zero,
-    One(T),
-    Two(T, T),
-    Three(T, T, T),
-}
-
-type CommandFn<T> = fn(&mut IntcodeMachine, Args<T>) -> Option<()>;
-// This probably isn't necessary to have as a separate struct
-struct Command<'a, T> {
-    machine: &'a mut IntcodeMachine,
-    command: CommandFn<T>,
-    args: Args<T>,
-}
-
-impl<'a> Command<'a, usize> {
-    fn apply(self) -> Option<()> {
-        let cmd = self.command;
-        cmd(self.machine, self.args)
-    }
-}
-
-#[derive(Debug)]
-struct IntcodeMachine {
-    memory: Vec<isize>,
-    cmd_ptr: usize,
-    phase_setting: Option<isize>,
-    input_signal: Receiver<isize>,
-    output: Sender<isize>,
+// Thanks Rosetta code... I solved with my own technique first, but this is much cleaner.
+// It was recursive at first; the recursion's queue, stack and loop counters now
+// live in the iterator so nothing is collected up front.
+struct Permutations<T> {
+    remaining: VecDeque<T>,
+    current: Vec<T>,
+    stack: Vec<usize>, // rotations of `remaining` left to try at each depth
 }
 
-impl IntcodeMachine {
-    fn new(
-        program: &[isize],
-        phase_setting: isize,
-        input_signal: Receiver<isize>,
-        output: Sender<isize>,
-    ) -> Self {
-        IntcodeMachine {
-            memory: program.to_vec(),
-            cmd_ptr: 0,
-            phase_setting: Some(phase_setting),
-            input_signal,
-            output,
-        }
-    }
-
-    fn decode_instruction(&mut self) -> (usize, Vec<Mode>) {
-        let instruction = self.memory[self.cmd_ptr] as usize;
-        self.cmd_ptr += 1;
-
-        let modes = (0..5)
-            .skip(2)
-            .map(|i| instruction / 10_usize.pow(i) % 10)
-            .map(Mode::from_code)
-            .collect();
-
-        (instruction % 100, modes)
-    }
-
-    fn get_current_memory_slice(&self, opcode: usize) -> &[isize] {
-        let arg_count = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            99 => 0,
-            x => panic!("unknown opcode: {}", x),
-        };
-
-        &self.memory[self.cmd_ptr..(self.cmd_ptr + arg_count)]
-    }
-
-    fn build_args(&self, raw_args: &[isize], modes: Vec<Mode>) -> Args<usize> {
-        let cmd_ptr = self.cmd_ptr;
-        let args = raw_args
-            .iter()
-            .zip(modes)
-            .enumerate()
-            .map(|(i, (&raw_arg, mode))| match mode {
-                Mode::Position => raw_arg as usize,
-                Mode::Value => cmd_ptr + i as usize,
-            })
-            .collect::<Vec<_>>();
-
-        match args.len() {
-            0 => Args::Zero,
-            1 => Args::One(args[0]),
-            2 => Args::Two(args[0], args[1]),
-            3 => Args::Three(args[0], args[1], args[2]),
-            _ => panic!("memory slice too big!"),
-        }
-    }
-
-    fn get_command(&mut self, opcode: usize, args: Args<usize>) -> Command<usize> {
-        let machine = self;
-        let command = match opcode {
-            1 => IntcodeMachine::add,
-            2 => IntcodeMachine::mul,
-            3 => IntcodeMachine::store,
-            4 => IntcodeMachine::push_output,
-            5 => IntcodeMachine::jump_if_true,
-            6 => IntcodeMachine::jump_if_false,
-            7 => IntcodeMachine::less_than,
-            8 => IntcodeMachine::equals,
-            99 => IntcodeMachine::halt,
-            x => panic!("unknown instruction: {}", x),
-        };
-
-        Command {
-            machine,
-            command,
-            args,
-        }
-    }
-
-    fn execute_step(&mut self) -> Option<()> {
-        let (opcode, modes) = self.decode_instruction();
-        let mem_slice = self.get_current_memory_slice(opcode);
-        let args = self.build_args(mem_slice, modes);
-
-        self.get_command(opcode, args).apply()
-    }
-
-    fn run(&mut self) {
-        self.for_each(|_| {});
-    }
-
-    fn add(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = self.memory[a_pos].add(self.memory[b_pos]);
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
-        }
-    }
-
-    fn mul(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = self.memory[a_pos].mul(self.memory[b_pos]);
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
-        }
-    }
+impl<T> Permutations<T> {
+    fn new(items: impl IntoIterator<Item = T>) -> Self {
+        let remaining = items.into_iter().collect::<VecDeque<_>>();
+        let stack = vec![remaining.len()];
 
-    fn store(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::One(storage_position) = args {
-            self.memory[storage_position] = self
-                .phase_setting
-                .take()
-                .or_else(|| self.input_signal.recv().ok())
-                .unwrap();
-            self.cmd_ptr += 1;
-            Some(())
-        } else {
-            None
+        Permutations {
+            remaining,
+            current: Vec::new(),
+            stack,
         }
     }
+}
 
-    fn push_output(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::One(data_position) = args {
-            self.output
-                .send(self.memory[data_position])
-                .expect("oh no!");
-            self.cmd_ptr += 1;
-            Some(())
-        } else {
-            None
-        }
-    }
+impl<T: Clone> Iterator for Permutations<T> {
+    type Item = Vec<T>;
 
-    fn jump_if_true(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Two(should_jump_pos, instruction_position) = args {
-            self.cmd_ptr = if self.memory[should_jump_pos] != 0 {
-                self.memory[instruction_position] as usize
-            } else {
-                self.cmd_ptr + 2
-            };
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rotations = self.stack.last_mut()?;
 
-            Some(())
-        } else {
-            None
-        }
-    }
+            if *rotations == 0 {
+                self.stack.pop();
 
-    fn jump_if_false(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Two(jump_on_zero_pos, instruction_position) = args {
-            self.cmd_ptr = if self.memory[jump_on_zero_pos] == 0 {
-                self.memory[instruction_position] as usize
-            } else {
-                self.cmd_ptr + 2
-            };
+                match self.current.pop() {
+                    Some(item) => self.remaining.push_back(item),
+                    // nothing to permute still has one (empty) permutation
+                    None if self.remaining.is_empty() => return Some(Vec::new()),
+                    None => {}
+                }
 
-            Some(())
-        } else {
-            None
-        }
-    }
+                continue;
+            }
 
-    fn less_than(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = (self.memory[a_pos] < self.memory[b_pos]) as isize;
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
-        }
-    }
+            *rotations -= 1;
+            self.current.push(self.remaining.pop_front().unwrap());
+            self.stack.push(self.remaining.len());
 
-    fn equals(&mut self, args: Args<usize>) -> Option<()> {
-        if let Args::Three(a_pos, b_pos, output_pos) = args {
-            self.memory[output_pos] = (self.memory[a_pos] == self.memory[b_pos]) as isize;
-            self.cmd_ptr += 3;
-            Some(())
-        } else {
-            None
+            if self.remaining.is_empty() {
+                return Some(self.current.clone());
+            }
         }
     }
-
-    fn halt<T>(&mut self, _args: Args<T>) -> Option<()> {
-        None
-    }
-}
-
-impl Iterator for IntcodeMachine {
-    type Item = ();
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.execute_step()
-    }
-}
-
-// Thanks Rosetta code... I solved with my own technique first, but this is much cleaner.
-fn generate_permutations<'a, T: Copy>(
-    stack: &'a mut Vec<T>,
-    queue: &'a mut VecDeque<T>,
-    mut output: Vec<Vec<T>>,
-) -> Vec<Vec<T>> {
-    if queue.is_empty() {
-        output.push(stack.clone());
-        return output;
-    }
-
-    (0..queue.len()).fold(output, |mut output, _| {
-        stack.push(queue.pop_front().unwrap());
-        output = generate_permutations(stack, queue, output);
-        queue.push_back(stack.pop().unwrap());
-
-        output
-    })
 }
 
-fn get_output(program: &[isize], phase_settings: Vec<isize>) -> isize {
-    let (tx, rx) = channel();
-    tx.send(0).ok();
-
+fn get_output(program: &[isize], phase_settings: Vec<isize>) -> Result<isize, IntcodeError> {
     phase_settings
         .into_iter()
-        .fold(rx, |output, signal| {
-            let (tx, rx) = channel();
-            let mut computer = IntcodeMachine::new(program, signal, output, tx);
-            computer.run();
-            rx
+        .try_fold(0, |signal, phase_setting| {
+            let mut computer = IntcodeMachine::new(program, VecDeque::new(), Vec::new());
+            computer.push_input(phase_setting);
+            computer.push_input(signal);
+            computer.run()?;
+            Ok(computer.output()[0])
         })
-        .recv()
-        .unwrap()
 }
 
-fn get_output_with_feedback_loop(program: &[isize], phase_settings: Vec<isize>) -> isize {
-    let (tx, rx) = channel();
+fn get_output_with_feedback_loop(
+    program: &[isize],
+    phase_settings: Vec<isize>,
+) -> Result<isize, IntcodeError> {
+    let mut computers = phase_settings
+        .into_iter()
+        .map(|phase_setting| {
+            let mut computer = IntcodeMachine::new(program, VecDeque::new(), Vec::new());
+            computer.push_input(phase_setting);
+            computer
+        })
+        .collect::<Vec<_>>();
 
-    let (output, computers) =
-        phase_settings
-            .into_iter()
-            .fold((rx, vec![]), |(output, mut computers), signal| {
-                let (tx, rx) = channel();
-                computers.push(IntcodeMachine::new(program, signal, output, tx));
-                (rx, computers)
-            });
+    let mut signal = 0;
 
-    tx.send(0).expect("sent-outer");
+    // the amplifiers take turns until the first one halts
+    loop {
+        for computer in computers.iter_mut() {
+            computer.push_input(signal);
 
-    computers.into_iter().for_each(|mut computer| {
-        spawn(move || computer.run());
-    });
-
-    output.into_iter().fold(0, |_, value| {
-        tx.send(value).unwrap_or(());
-        value
-    })
+            match computer.run_until_output()? {
+                Some(output) => signal = output,
+                None => return Ok(signal),
+            }
+        }
+    }
 }
 
-fn solve_1(program: &[isize]) -> isize {
-    generate_permutations(&mut Vec::new(), &mut (0..=4).collect(), Vec::new())
-        .into_iter()
+fn solve_1(program: &[isize]) -> Result<isize, IntcodeError> {
+    Permutations::new(0..=4)
         .map(|settings| get_output(program, settings))
-        .max()
-        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .map(|outputs| outputs.into_iter().max().unwrap())
 }
 
-fn solve_2(program: &[isize]) -> isize {
-    generate_permutations(&mut Vec::new(), &mut (5..=9).collect(), Vec::new())
-        .into_iter()
+fn solve_2(program: &[isize]) -> Result<isize, IntcodeError> {
+    Permutations::new(5..=9)
         .map(|settings| get_output_with_feedback_loop(program, settings))
-        .max()
-        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .map(|outputs| outputs.into_iter().max().unwrap())
 }
 
 fn main() {
@@ -345,8 +114,17 @@ fn main() {
         std::process::exit(1);
     });
 
-    println!("first solution: {:?}", solve_1(&input));
-    println!("second solution: {:?}", solve_2(&input));
+    let first = solve_1(&input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    println!("first solution: {:?}", first);
+
+    let second = solve_2(&input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    println!("second solution: {:?}", second);
 }
 
 fn get_input() -> Result<Vec<isize>, Box<dyn Error>> {
@@ -356,3 +134,35 @@ fn get_input() -> Result<Vec<isize>, Box<dyn Error>> {
         .map(|line| line.parse())
         .collect::<Result<Vec<isize>, std::num::ParseIntError>>()?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutations_of_three() {
+        let permutations = Permutations::new([1, 2, 3].iter().copied()).collect::<Vec<_>>();
+
+        assert_eq!(
+            permutations,
+            vec![
+                vec![1, 2, 3],
+                vec![1, 3, 2],
+                vec![2, 3, 1],
+                vec![2, 1, 3],
+                vec![3, 1, 2],
+                vec![3, 2, 1],
+            ]
+        );
+    }
+
+    #[test]
+    fn permutation_counts() {
+        assert_eq!(Permutations::new(0..=4).count(), 120);
+        assert_eq!(Permutations::new(0..1).collect::<Vec<_>>(), vec![vec![0]]);
+        assert_eq!(
+            Permutations::new(Vec::<isize>::new()).collect::<Vec<_>>(),
+            vec![Vec::new()]
+        );
+    }
+}
@@ -1,67 +1,261 @@
-use std::{error::Error, fs, string::ToString};
+use image::{GrayAlphaImage, LumaA};
+use std::{error::Error, fmt, fs, string::ToString};
 
+/// A borrowed layer, for reading without copying.
 #[derive(Debug)]
-struct Layer<'a> {
+struct LayerView<'a> {
     data: &'a [usize],
     width: usize,
     height: usize,
 }
 
-impl<'a> Layer<'a> {
+impl<'a> LayerView<'a> {
     fn count_occurrences_of(&self, digit: usize) -> usize {
         self.data.iter().filter(|x| **x == digit).count()
     }
-}
 
-fn get_layers(data: &[usize], width: usize, height: usize) -> Vec<Layer> {
-    data.chunks(width * height)
-        .map(|data| Layer {
+    /// The number of 1s times the number of 2s.
+    fn checksum(&self) -> usize {
+        self.count_occurrences_of(1) * self.count_occurrences_of(2)
+    }
+
+    fn pixel_at(&self, row: usize, col: usize) -> usize {
+        assert!(
+            row < self.height && col < self.width,
+            "({}, {}) is outside the {}x{} layer",
+            row,
+            col,
+            self.width,
+            self.height
+        );
+
+        self.data[row * self.width + col]
+    }
+
+    /// Lays this layer over `below`, letting its transparent pixels show through.
+    fn composite_with(&self, below: &LayerView) -> OwnedLayer {
+        let data = self
+            .data
+            .iter()
+            .zip(below.data)
+            .map(|(&top, &bottom)| if top == TRANSPARENT { bottom } else { top })
+            .collect();
+
+        OwnedLayer {
             data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// A layer that owns its pixels, so it can be kept around after the input is gone.
+#[derive(Clone, Debug, PartialEq)]
+struct OwnedLayer {
+    data: Vec<usize>,
+    width: usize,
+    height: usize,
+}
+
+impl OwnedLayer {
+    fn from_slice(data: &[usize], width: usize, height: usize) -> Self {
+        OwnedLayer {
+            data: data.to_vec(),
             width,
             height,
-        })
+        }
+    }
+
+    fn view(&self) -> LayerView<'_> {
+        LayerView {
+            data: &self.data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ImageError {
+    /// There are no digits, so there isn't a single layer to decode.
+    Empty,
+    /// The digits don't split evenly into layers of `stride` pixels.
+    RaggedInput { len: usize, stride: usize },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::Empty => write!(f, "the image has no digits"),
+            ImageError::RaggedInput { len, stride } => write!(
+                f,
+                "{} digits can't be split into layers of {} pixels",
+                len, stride
+            ),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+// a short last layer is kept as-is
+fn get_layers(data: &[usize], width: usize, height: usize) -> Vec<OwnedLayer> {
+    data.chunks(width * height)
+        .map(|data| OwnedLayer::from_slice(data, width, height))
         .collect()
 }
 
-fn get_image(layers: Vec<Layer>, width: usize) -> String {
+/// Like `get_layers`, but rejects input that doesn't fill its last layer,
+/// e.g. a truncated download.
+// usize::is_multiple_of needs Rust 1.87, newer than the workspace asks for
+#[allow(clippy::manual_is_multiple_of)]
+fn try_get_layers(
+    data: &[usize],
+    width: usize,
+    height: usize,
+) -> Result<Vec<OwnedLayer>, ImageError> {
+    let stride = width * height;
+
+    if data.is_empty() {
+        return Err(ImageError::Empty);
+    }
+
+    if stride == 0 || data.len() % stride != 0 {
+        return Err(ImageError::RaggedInput {
+            len: data.len(),
+            stride,
+        });
+    }
+
+    Ok(get_layers(data, width, height))
+}
+
+const TRANSPARENT: usize = 2;
+
+// stacks the layers top-down, letting transparent pixels show whatever is
+// underneath
+fn composite(layers: &[OwnedLayer], transparent: usize) -> Vec<usize> {
     let image = layers[0].data.to_vec();
-    let raw_image = layers.into_iter().skip(1).fold(image, |mut image, layer| {
+    layers.iter().skip(1).fold(image, |mut image, layer| {
         image.iter_mut().enumerate().for_each(|(i, x)| {
-            if *x == 2 {
+            if *x == transparent {
                 *x = layer.data[i];
             }
         });
 
         image
-    });
+    })
+}
 
-    raw_image
-        .into_iter()
-        .map(|x| x.to_string())
-        .map(|x| if &x == "1" { "0" } else { " " })
-        .collect::<Vec<_>>()
+/// The characters used to draw the image as text.
+#[derive(Debug)]
+struct RenderConfig {
+    on_char: char,
+    off_char: char,
+    transparent_char: char,
+    separator: char,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            on_char: '0',
+            off_char: ' ',
+            transparent_char: ' ',
+            separator: ' ',
+        }
+    }
+}
+
+/// The composited pixels, one `Vec` per row, for callers that want to draw
+/// the image themselves.
+fn decode_grid(layers: &[OwnedLayer], width: usize) -> Vec<Vec<usize>> {
+    composite(layers, TRANSPARENT)
         .chunks(width)
-        .map(|chunk| chunk.join(" "))
+        .map(<[usize]>::to_vec)
+        .collect()
+}
+
+fn get_image(layers: Vec<OwnedLayer>, width: usize, config: &RenderConfig) -> String {
+    decode_grid(&layers, width)
+        .iter()
+        .map(|row| render(row, width, config))
         .collect::<Vec<_>>()
         .join("\n")
 }
 
-fn solve_1(input: &[usize]) -> usize {
-    let width = 25;
-    let height = 6;
-    let layer = get_layers(input, width, height)
-        .into_iter()
-        .min_by(|a, b| a.count_occurrences_of(0).cmp(&b.count_occurrences_of(0)))
-        .expect("No min!?");
+/// Like `get_image`, but draws 1s as `on` and everything else as `off`.
+fn get_image_with_glyphs(layers: Vec<OwnedLayer>, width: usize, on: char, off: char) -> String {
+    let config = RenderConfig {
+        on_char: on,
+        off_char: off,
+        transparent_char: off,
+        ..RenderConfig::default()
+    };
+
+    get_image(layers, width, &config)
+}
+
+fn render(pixels: &[usize], width: usize, config: &RenderConfig) -> String {
+    render_to_lines(pixels, width, config).join("\n")
+}
+
+/// One string per row of the image.
+fn render_to_lines(pixels: &[usize], width: usize, config: &RenderConfig) -> Vec<String> {
+    pixels
+        .iter()
+        .map(|&x| match x {
+            1 => config.on_char,
+            TRANSPARENT => config.transparent_char,
+            _ => config.off_char,
+        })
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .chunks(width)
+        .map(|chunk| chunk.join(&config.separator.to_string()))
+        .collect()
+}
+
+/// Writes the decoded image as a PNG: 1s are white, 0s are black and anything
+/// still 2 after compositing is transparent.
+fn render_png(layers: &[OwnedLayer], path: &str) -> Result<(), Box<dyn Error>> {
+    let (top, below) = layers.split_first().ok_or("there are no layers to draw")?;
+    let image = below.iter().fold(top.clone(), |image, layer| {
+        image.view().composite_with(&layer.view())
+    });
+
+    if image.data.len() != image.width * image.height {
+        return Err("the layers don't match the image size".into());
+    }
+
+    let image = image.view();
+    GrayAlphaImage::from_fn(image.width as u32, image.height as u32, |x, y| match image
+        .pixel_at(y as usize, x as usize)
+    {
+        0 => LumaA([0, 255]),
+        1 => LumaA([255, 255]),
+        _ => LumaA([0, 0]),
+    })
+    .save(path)?;
+
+    Ok(())
+}
+
+fn fewest_zeros_layer(layers: &[OwnedLayer]) -> &OwnedLayer {
+    layers
+        .iter()
+        .min_by_key(|layer| layer.view().count_occurrences_of(0))
+        .expect("No min!?")
+}
 
-    layer.count_occurrences_of(1) * layer.count_occurrences_of(2)
+fn solve_1(input: &[usize], width: usize, height: usize) -> Result<usize, ImageError> {
+    let layers = try_get_layers(input, width, height)?;
+    Ok(fewest_zeros_layer(&layers).view().checksum())
 }
 
-fn solve_2(input: &[usize]) -> String {
-    let width = 25;
-    let height = 6;
-    let layers = get_layers(input, width, height);
-    get_image(layers, width)
+fn solve_2(input: &[usize], width: usize, height: usize) -> Result<String, ImageError> {
+    let layers = try_get_layers(input, width, height)?;
+    Ok(get_image_with_glyphs(layers, width, '0', ' '))
 }
 
 fn main() {
@@ -69,11 +263,26 @@ fn main() {
         eprintln!("{}", err);
         std::process::exit(1);
     });
+    let (width, height) = (25, 6);
+
+    let image = solve_1(&input, width, height)
+        .and_then(|first| {
+            println!("first solution: {:?}", first);
+            solve_2(&input, width, height)
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
 
-    println!("first solution: {:?}", solve_1(&input));
     println!("second solution:");
-    print!("{}", solve_2(&input));
+    print!("{}", image);
     println!();
+
+    let layers = get_layers(&input, width, height);
+    if let Err(err) = render_png(&layers, "output.png") {
+        eprintln!("couldn't save output.png: {}", err);
+    }
 }
 
 fn get_input() -> Result<Vec<usize>, Box<dyn Error>> {
@@ -100,6 +309,44 @@ mod tests {
 
         assert_eq!(layer_1.data, &[1, 2, 3, 4, 5, 6]);
         assert_eq!(layer_2.data, &[7, 8, 9, 0, 1, 2]);
+        assert_eq!(solve_1(&input, width, height), Ok(1));
+    }
+
+    #[test]
+    fn pixel_at() {
+        let layers = get_layers(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2], 3, 2);
+
+        assert_eq!(layers[0].view().pixel_at(0, 0), 1);
+        assert_eq!(layers[0].view().pixel_at(0, 2), 3);
+        assert_eq!(layers[1].view().pixel_at(1, 0), 0);
+        assert_eq!(fewest_zeros_layer(&layers), &layers[0]);
+        assert_eq!(layers[1].view().checksum(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "(0, 3) is outside the 3x2 layer")]
+    fn pixel_at_out_of_bounds() {
+        // would wrap round to (1, 0) if the column weren't checked
+        get_layers(&[1, 2, 3, 4, 5, 6], 3, 2)[0]
+            .view()
+            .pixel_at(0, 3);
+    }
+
+    #[test]
+    fn owned_layers_outlive_input() {
+        let layers = {
+            let input = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2];
+            get_layers(&input, 3, 2)
+        };
+
+        assert_eq!(
+            layers,
+            vec![
+                OwnedLayer::from_slice(&[1, 2, 3, 4, 5, 6], 3, 2),
+                OwnedLayer::from_slice(&[7, 8, 9, 0, 1, 2], 3, 2)
+            ]
+        );
+        assert_eq!(layers[1].view().count_occurrences_of(0), 1);
     }
 
     #[test]
@@ -109,8 +356,116 @@ mod tests {
         let height = 2;
 
         let layers = get_layers(&input, width, height);
-        let image = get_image(layers, width);
+        let image = get_image(layers, width, &RenderConfig::default());
 
         assert_eq!(image, "  0\n0  ");
+        assert_eq!(
+            decode_grid(&get_layers(&input, width, height), width),
+            vec![vec![0, 1], vec![1, 0]]
+        );
+        assert_eq!(solve_2(&input, width, height), Ok(image));
+    }
+
+    #[test]
+    fn composite_other_transparent() {
+        let input = vec![9, 3, 9, 9, 4, 9, 5, 9, 9, 7, 6, 8];
+        let layers = get_layers(&input, 2, 2);
+
+        assert_eq!(composite(&layers, 9), vec![4, 3, 5, 8]);
+        assert_eq!(composite(&layers[..1], 9), vec![9, 3, 9, 9]);
+    }
+
+    #[test]
+    fn composite_single_layer() {
+        let input = vec![0, 2, 1, 2];
+        let layers = get_layers(&input, 2, 2);
+
+        assert_eq!(composite(&layers, TRANSPARENT), input);
+        assert_eq!(render(&input, 2, &RenderConfig::default()), "   \n0  ");
+    }
+
+    #[test]
+    fn composite_two_layers() {
+        let input = vec![0, 2, 2, 2, 1, 1, 2, 2];
+        let layers = get_layers(&input, 2, 2);
+
+        assert_eq!(
+            layers[0].view().composite_with(&layers[1].view()),
+            OwnedLayer::from_slice(&[0, 1, 2, 2], 2, 2)
+        );
+    }
+
+    #[test]
+    fn render_config() {
+        let config = RenderConfig {
+            on_char: '#',
+            off_char: '.',
+            transparent_char: '?',
+            separator: '|',
+        };
+
+        assert_eq!(
+            render_to_lines(&[1, 0, 2, 1, 1, 0], 3, &config),
+            vec!["#|.|?", "#|#|."]
+        );
+        assert_eq!(
+            render_to_lines(&[1, 0, 2, 1], 2, &RenderConfig::default()),
+            vec!["0  ", "  0"]
+        );
+    }
+
+    #[test]
+    fn custom_glyphs() {
+        let input = vec![0, 2, 2, 2, 1, 1, 2, 2, 2, 2, 1, 2, 0, 0, 0, 0];
+        let layers = get_layers(&input, 2, 2);
+
+        assert_eq!(get_image_with_glyphs(layers, 2, '#', '.'), ". #\n# .");
+    }
+
+    #[test]
+    fn ragged_input() {
+        let input = vec![1, 2, 3, 4, 5, 6, 7];
+
+        assert_eq!(
+            try_get_layers(&input, 3, 2),
+            Err(ImageError::RaggedInput { len: 7, stride: 6 })
+        );
+        assert_eq!(get_layers(&input, 3, 2)[1].data, vec![7]);
+        assert_eq!(try_get_layers(&[], 3, 2), Err(ImageError::Empty));
+        assert_eq!(solve_1(&[], 3, 2), Err(ImageError::Empty));
+        assert_eq!(solve_2(&[], 3, 2), Err(ImageError::Empty));
+    }
+
+    #[test]
+    fn mismatched_layer_size() {
+        let input = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 0];
+
+        assert_eq!(
+            solve_1(&input, 3, 2),
+            Err(ImageError::RaggedInput { len: 10, stride: 6 })
+        );
+        assert!(solve_2(&input, 3, 2).is_err());
+        assert!(solve_1(&input, 0, 2).is_err());
+        assert_eq!(solve_1(&input, 5, 2), Ok(1));
+    }
+
+    #[test]
+    fn render_png_test() {
+        let input = vec![0, 2, 2, 2, 1, 1, 2, 2, 2, 2, 1, 2, 0, 0, 0, 2];
+        let layers = get_layers(&input, 2, 2);
+        let path = std::env::temp_dir().join("problem-8-render_png_test.png");
+        let path = path.to_str().unwrap();
+
+        assert!(render_png(&[], path).is_err());
+        assert!(render_png(&get_layers(&input[..7], 2, 2), path).is_err());
+        render_png(&layers, path).unwrap();
+        let image = image::open(path).unwrap().into_luma_alpha8();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(
+            image.pixels().map(|pixel| pixel.0).collect::<Vec<_>>(),
+            vec![[0, 255], [255, 255], [255, 255], [0, 0]]
+        );
     }
 }